@@ -1,15 +1,21 @@
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 use crate::{
-    Color, ConfigFile, Environment, ServiceCommand, ServiceConfig, ServiceManagerKind, ServiceName,
+    Color, ConfigFile, ConfigOverride, ConfigOverrides, Environment, ServiceCommand, ServiceConfig,
+    ServiceManagerKind, ServiceName,
 };
 
 mod boilerplate_args;
+mod config_args;
 mod deployment_args;
+mod logs_args;
 mod service_args;
 
 pub use boilerplate_args::*;
+pub use config_args::*;
 pub use deployment_args::DeploymentArgs;
+pub use logs_args::LogsArgs;
 pub use service_args::ServiceArgs;
 
 #[derive(Clone, Debug, Default, Parser)]
@@ -42,14 +48,33 @@ pub struct Args {
     #[clap(long, global = true)]
     pub system: bool,
 
-    /// Color output.
-    #[clap(long, global = true, default_value = "auto")]
-    pub color: Color,
+    /// Color output. Defaults to auto.
+    #[clap(long, global = true)]
+    pub color: Option<Color>,
+
+    /// Reject unknown config keys and values that can't work.
+    #[clap(long, global = true)]
+    pub strict: bool,
 
     /// The path to the configuration file.
     #[clap(long, short)]
     pub config_file: Option<ConfigFile>,
 
+    /// Another directory to search for configuration files. Can be repeated,
+    /// later directories taking precedence.
+    #[clap(long = "config-path", global = true)]
+    pub config_paths: Vec<PathBuf>,
+
+    /// Set a config value, as `key=value` with a dotted key. Takes
+    /// precedence over every other source, and the key has to be known.
+    /// Can be repeated.
+    #[clap(long, global = true, value_name = "KEY=VALUE", value_parser = ConfigOverride::parse)]
+    pub set: Vec<ConfigOverride>,
+
+    /// Set a config value from JSON, as `key=json`. Applied after `--set`.
+    #[clap(long, global = true, value_name = "KEY=JSON", value_parser = ConfigOverride::parse_json)]
+    pub set_json: Vec<ConfigOverride>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -61,6 +86,8 @@ pub enum Commands {
     Deploy(DeploymentArgs),
     Generate(BoilerplateArgs),
     Container(DeploymentArgs),
+    Config(ConfigArgs),
+    Logs(LogsArgs),
 }
 
 impl From<ServiceArgs> for Commands {
@@ -84,6 +111,42 @@ impl Args {
             .unwrap_or_else(|| service_config.name().into())
     }
 
+    /// The config values set by flags that were passed, leaving out
+    /// anything left to its default.
+    pub fn arguments(&self) -> ConfigOverrides {
+        let config = serde_json::to_value(crate::Configuration::from(self)).unwrap_or_default();
+        let passed = [
+            ("verbosity", self.verbose > 0),
+            ("server.host", self.host.is_some()),
+            ("server.port", self.port.is_some()),
+            ("environment", self.environment.is_some()),
+            ("color", self.color.is_some()),
+            ("service.name", self.name.is_some()),
+            ("service.service-manager", self.service_manager.is_some()),
+            ("service.system", self.system),
+            ("strict", self.strict),
+        ];
+
+        ConfigOverrides(
+            passed
+                .into_iter()
+                .filter(|(_, passed)| *passed)
+                .filter_map(|(key, _)| {
+                    let value = config.pointer(&format!("/{}", key.replace('.', "/")))?;
+
+                    Some(ConfigOverride {
+                        key: key.to_string(),
+                        value: value.clone(),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    pub fn overrides(&self) -> ConfigOverrides {
+        ConfigOverrides(self.set.iter().chain(&self.set_json).cloned().collect())
+    }
+
     pub fn service(&self) -> ServiceConfig {
         ServiceConfig::builder()
             .maybe_name(self.name.clone())
@@ -151,13 +214,21 @@ fn setting_environment_with_args() -> Result<(), Box<dyn std::error::Error>> {
         ("app", None),
         (
             "app --environment development",
-            Some(Environment::Development),
+            Some(Environment::DEVELOPMENT),
         ),
         (
             "app --environment production",
-            Some(Environment::Production),
+            Some(Environment::PRODUCTION),
+        ),
+        ("app --environment test", Some(Environment::TEST)),
+        (
+            "app --environment staging",
+            Some(Environment::new("staging")?),
+        ),
+        (
+            "app --environment preview-123",
+            Some(Environment::new("preview-123")?),
         ),
-        ("app --environment test", Some(Environment::Test)),
     ];
 
     for (input, expected) in expectations {
@@ -168,6 +239,9 @@ fn setting_environment_with_args() -> Result<(), Box<dyn std::error::Error>> {
             Configuration::builder().maybe_environment(expected).build()
         );
     }
+
+    assert!(Args::try_parse_from("app --environment Not/Valid".split_whitespace()).is_err());
+
     Ok(())
 }
 
@@ -0,0 +1,75 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Parser, PartialEq)]
+#[clap(rename_all = "kebab-case")]
+pub struct ConfigArgs {
+    #[clap(subcommand)]
+    pub command: Option<ConfigCommand>,
+}
+
+#[derive(Clone, Debug, Subcommand, PartialEq)]
+#[clap(rename_all = "kebab-case")]
+pub enum ConfigCommand {
+    /// Show every resolved config key, its value, and the source that set it.
+    Explain,
+    /// Print a JSON Schema for config files, for editor validation and completion.
+    Schema,
+    /// Encrypt each value in a config file, writing it alongside with `.enc` added.
+    Encrypt { path: PathBuf },
+    /// Print an encrypted config file with its values decrypted.
+    Decrypt { path: PathBuf },
+    /// Edit an encrypted config file in $EDITOR, encrypting it again on save.
+    Edit { path: PathBuf },
+    /// Create a key for encrypted config files, if there isn't one, and show where it is.
+    Key,
+    /// Show how the merged config for one environment differs from another.
+    Diff {
+        from: String,
+        to: String,
+        /// Compare two config file names instead of two environments.
+        #[clap(long)]
+        files: bool,
+        /// Print the changes as JSON.
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+#[test]
+fn config_commands() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::{Args, Commands};
+
+    let expectations = [
+        ("app config", None),
+        ("app config explain", Some(ConfigCommand::Explain)),
+        ("app config schema", Some(ConfigCommand::Schema)),
+        (
+            "app config edit app.production.yaml.enc",
+            Some(ConfigCommand::Edit {
+                path: "app.production.yaml.enc".into(),
+            }),
+        ),
+        ("app config key", Some(ConfigCommand::Key)),
+        (
+            "app config diff development production --json",
+            Some(ConfigCommand::Diff {
+                from: "development".into(),
+                to: "production".into(),
+                files: false,
+                json: true,
+            }),
+        ),
+    ];
+
+    for (input, expected) in expectations {
+        let cli = Args::try_parse_from(input.split_whitespace())?;
+
+        assert_eq!(
+            cli.command,
+            Some(Commands::Config(ConfigArgs { command: expected }))
+        );
+    }
+
+    Ok(())
+}
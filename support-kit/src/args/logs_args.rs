@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use clap::Parser;
+
+use crate::{LogLevel, LogQuery};
+
+/// Read what the file loggers wrote, here or on a deployed host.
+#[derive(Clone, Debug, Default, Parser, PartialEq)]
+#[clap(rename_all = "kebab-case")]
+pub struct LogsArgs {
+    /// Read the logs on the deployed host with this address instead.
+    #[clap(long, value_name = "HOST")]
+    pub on: Option<String>,
+    /// Keep printing events as they're written.
+    #[clap(long, short)]
+    pub follow: bool,
+    /// Only print the last few events.
+    #[clap(long)]
+    pub lines: Option<usize>,
+    /// The least severe level to print.
+    #[clap(long, value_enum)]
+    pub level: Option<LogLevel>,
+    /// Only print events from a target and the modules under it.
+    #[clap(long)]
+    pub target: Option<String>,
+    /// Only print events inside a span with this name.
+    #[clap(long)]
+    pub span: Option<String>,
+    /// Only print events with a field set to a value. Can be repeated.
+    #[clap(long = "field", value_name = "NAME=VALUE", value_parser = parse_field)]
+    pub fields: Vec<(String, String)>,
+    /// Only print events at or after this RFC 3339 time.
+    #[clap(long)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only print events before this RFC 3339 time.
+    #[clap(long)]
+    pub until: Option<DateTime<Utc>>,
+    /// Print events as the JSON they were written as.
+    #[clap(long)]
+    pub json: bool,
+}
+
+impl LogsArgs {
+    pub fn query(&self) -> LogQuery {
+        LogQuery::builder()
+            .maybe_level(self.level)
+            .maybe_target(self.target.clone())
+            .maybe_span(self.span.clone())
+            .maybe_since(self.since)
+            .maybe_until(self.until)
+            .fields(
+                self.fields
+                    .iter()
+                    .cloned()
+                    .collect::<std::collections::BTreeMap<_, _>>(),
+            )
+            .build()
+    }
+
+    /// Whether every line is printed, even ones that aren't events.
+    pub fn unfiltered(&self) -> bool {
+        self.level.is_none()
+            && self.target.is_none()
+            && self.span.is_none()
+            && self.fields.is_empty()
+            && self.since.is_none()
+            && self.until.is_none()
+    }
+}
+
+fn parse_field(field: &str) -> Result<(String, String), String> {
+    field
+        .split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got {field:?}"))
+}
+
+#[test]
+fn logs_commands() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::{Args, Commands};
+
+    let args = Args::try_parse_from(
+        "app logs --on web-1 -f --lines 20 --level warn --target my_app::db --field user=7 --since 2024-05-01T12:00:00Z"
+            .split_whitespace(),
+    )?;
+
+    assert_eq!(args.host, None);
+    assert_eq!(
+        args.command,
+        Some(Commands::Logs(LogsArgs {
+            on: Some("web-1".into()),
+            follow: true,
+            lines: Some(20),
+            level: Some(LogLevel::Warn),
+            target: Some("my_app::db".into()),
+            fields: vec![("user".into(), "7".into())],
+            since: Some("2024-05-01T12:00:00Z".parse()?),
+            ..Default::default()
+        }))
+    );
+
+    let Some(Commands::Logs(logs)) = args.command else {
+        unreachable!()
+    };
+    let query = logs.query();
+
+    assert_eq!(query.level, Some(LogLevel::Warn));
+    assert_eq!(query.fields.get("user").map(String::as_str), Some("7"));
+    assert!(!logs.unfiltered());
+    assert!(Args::try_parse_from("app logs --field user".split_whitespace()).is_err());
+
+    Ok(())
+}
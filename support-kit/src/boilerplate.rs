@@ -220,3 +220,63 @@ fn test_action() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn config_schema() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::Configuration;
+
+    // Other tests clear the temp dir, so this one keeps to its own
+    // directory and makes sure it exists.
+    let path = std::env::temp_dir().join(format!("config-schema-{}", std::process::id()));
+    let controller = BoilerplateControl::builder()
+        .context(Configuration::default())
+        .base_path(&path)
+        .build();
+
+    std::fs::create_dir_all(&path)?;
+    controller.write(BoilerplatePreset::ConfigSchema)?;
+
+    let template = controller.template(BoilerplatePreset::ConfigSchema);
+    assert_eq!(template.file(), path.join("support-kit.schema.json"));
+
+    let written: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(template.file())?)?;
+
+    assert_eq!(written, Configuration::schema());
+    std::fs::remove_dir_all(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn crate_config_sections() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::{ConfigSection, Configuration};
+
+    #[derive(Default, schemars::JsonSchema, serde::Serialize)]
+    struct AppConfig {
+        workers: u16,
+    }
+
+    let path = std::env::temp_dir().join("crate-config-sections");
+    let sections = vec![ConfigSection::new::<AppConfig>("app")];
+    let controller = BoilerplateControl::builder()
+        .context(Configuration::default())
+        .base_path(&path)
+        .sections(sections.clone())
+        .build();
+
+    controller.write(BoilerplatePreset::CrateConfig)?;
+    controller.write(BoilerplatePreset::ConfigSchema)?;
+
+    let config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path.join("support-kit.json"))?)?;
+    let schema: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(
+        path.join("support-kit.schema.json"),
+    )?)?;
+
+    assert_eq!(config["app"], serde_json::json!({ "workers": 0 }));
+    assert_eq!(schema, Configuration::schema_with(&sections));
+    std::fs::remove_dir_all(&path).unwrap_or_default();
+
+    Ok(())
+}
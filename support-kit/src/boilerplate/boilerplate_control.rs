@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::Configuration;
+use crate::{ConfigSection, Configuration};
 
 use super::{BoilerplateContext, BoilerplatePreset, BoilerplateTemplate};
 
@@ -12,6 +12,8 @@ pub struct BoilerplateControl {
     pub context: BoilerplateContext,
     #[builder(into, default)]
     pub base_path: PathBuf,
+    #[builder(default, into)]
+    pub sections: Vec<ConfigSection>,
 }
 
 impl BoilerplateControl {
@@ -19,6 +21,19 @@ impl BoilerplateControl {
         Ok(self.template(preset).write(&self.context)?)
     }
 
+    /// The configuration with each section's defaults added.
+    pub fn config_value(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(&self.config).unwrap_or_default();
+
+        if let Some(config) = value.as_object_mut() {
+            for section in &self.sections {
+                config.insert(section.key().to_string(), section.defaults());
+            }
+        }
+
+        value
+    }
+
     pub fn template(&self, preset: BoilerplatePreset) -> BoilerplateTemplate {
         preset.init(&self)
     }
@@ -1,6 +1,8 @@
 use clap::Subcommand;
 use strum::VariantArray;
 
+use crate::Configuration;
+
 use super::{BoilerplateControl, BoilerplateTemplate};
 
 #[derive(Clone, Debug, Subcommand, PartialEq, VariantArray)]
@@ -11,6 +13,7 @@ pub enum BoilerplatePreset {
     TestAction,
     CargoConfig,
     CrateConfig,
+    ConfigSchema,
 }
 
 impl BoilerplatePreset {
@@ -46,10 +49,21 @@ impl BoilerplatePreset {
                 .path(&controller.base_path)
                 .file_name(format!("{name}.json", name = controller.config.name()))
                 .source(
-                    serde_json::to_string(&controller.config)
+                    serde_json::to_string(&controller.config_value())
                         .expect("Failed to serialize configuration"),
                 )
                 .build(),
+            Self::ConfigSchema => BoilerplateTemplate::builder()
+                .path(&controller.base_path)
+                .file_name(format!(
+                    "{name}.schema.json",
+                    name = controller.config.name()
+                ))
+                .source(
+                    serde_json::to_string_pretty(&Configuration::schema_with(&controller.sections))
+                        .expect("Failed to serialize configuration schema"),
+                )
+                .build(),
         }
     }
 }
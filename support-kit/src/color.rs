@@ -1,7 +1,8 @@
 use clap::ValueEnum;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(ValueEnum, Default, Deserialize, Clone, Copy, Debug, Serialize, PartialEq)]
+#[derive(ValueEnum, Default, Deserialize, JsonSchema, Clone, Copy, Debug, Serialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 #[clap(rename_all = "kebab-case")]
 pub enum Color {
@@ -12,6 +13,15 @@ pub enum Color {
 }
 
 impl Color {
+    /// Whether to color output going to a terminal, or somewhere else.
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            Color::Always => true,
+            Color::Auto => terminal && std::env::var_os("NO_COLOR").is_none(),
+            Color::Never => false,
+        }
+    }
+
     pub fn init(self) {
         // Set a supports-color override based on the variable passed in.
         match self {
@@ -1,64 +1,89 @@
 mod config_definition;
+mod config_diff;
+mod config_encryption;
 mod config_env_var;
+mod config_explanation;
 mod config_file;
 mod config_format;
 mod config_manifest;
+mod config_origin;
+mod config_override;
+mod config_search_path;
+mod config_secrets;
+mod config_section;
 mod config_sources;
+mod config_validation;
+mod config_variant;
+mod config_watcher;
 mod configuration;
 
-use config_definition::ConfigDefinition;
 use config_env_var::ConfigEnvVar;
-use config_format::ConfigFormat;
 
+pub use config_definition::ConfigDefinition;
+pub use config_diff::{ConfigChange, ConfigDiff};
+pub use config_encryption::{ConfigKey, EncryptedConfigFile};
+pub use config_explanation::{ConfigExplanation, ConfigExplanationEntry};
 pub use config_file::ConfigFile;
+pub use config_format::{ConfigFileFormat, ConfigFormat, CustomFormat};
 pub use config_manifest::ConfigManifest;
+pub use config_origin::ConfigOrigin;
+pub use config_override::{ConfigOverride, ConfigOverrides};
+pub use config_search_path::ConfigSearchPath;
+pub use config_secrets::{ConfigSecrets, SecretReference};
+pub use config_section::ConfigSection;
 pub use config_sources::ConfigSources;
+pub use config_validation::{ConfigIssue, ConfigValidation};
+pub use config_variant::ConfigVariant;
+pub use config_watcher::ConfigWatcher;
 pub use configuration::Configuration;
 
 #[test]
 fn config_file_sets_sources() -> Result<(), Box<dyn std::error::Error>> {
     use clap::Parser;
 
-    use crate::{config::ConfigFormat, Args, Environment, SupportControl};
+    use crate::{
+        config::{ConfigFileFormat, ConfigFormat},
+        Args, Environment, SupportControl,
+    };
 
-    for format in ConfigFormat::all() {
+    for format in ConfigFormat::BUILT_IN {
         let envs = Environment::all();
         for env in envs {
             let expectations = [
                 ("app", None, None, "support-kit"),
                 (
                     &format!("app --environment {env}"),
-                    Some(env),
+                    Some(env.clone()),
                     None,
                     "support-kit",
                 ),
                 (
                     &format!("app --config-file custom.config --environment {env}"),
-                    Some(env),
+                    Some(env.clone()),
                     None,
                     "custom.config",
                 ),
                 (
                     &format!("app --name custom-app-name --environment {env}"),
-                    Some(env),
+                    Some(env.clone()),
                     None,
                     "custom-app-name",
                 ),
                 (
                     &format!("app --config-file custom.config --environment {env}"),
-                    Some(env),
+                    Some(env.clone()),
                     Some("custom-app-name"),
                     "custom.config",
                 ),
                 (
                     &format!("app --environment {env}"),
-                    Some(env),
+                    Some(env.clone()),
                     Some("custom-app-name"),
                     "custom-app-name",
                 ),
                 (
                     &format!("app --name custom-app-name --environment {env}"),
-                    Some(env),
+                    Some(env.clone()),
                     Some("legacy-app-name"),
                     "custom-app-name",
                 ),
@@ -66,7 +91,7 @@ fn config_file_sets_sources() -> Result<(), Box<dyn std::error::Error>> {
 
             for (input, env, crate_name, expected) in expectations {
                 figment::Jail::expect_with(|jail| {
-                    let env = env.unwrap_or_default();
+                    let env = env.clone().unwrap_or_default();
 
                     if let Some(crate_name) = crate_name {
                         jail.set_env("CARGO_PKG_NAME", crate_name);
@@ -87,7 +112,7 @@ fn config_file_sets_sources() -> Result<(), Box<dyn std::error::Error>> {
                             .definitions(bon::vec![
                                 (format, expected),
                                 expected,
-                                (format, expected, env),
+                                (format, expected, env.clone()),
                                 (expected, env),
                             ])
                             .build()
@@ -1,12 +1,19 @@
 use figment::{
-    providers::{Env, Format, Json, Toml, Yaml},
-    Provider,
+    providers::{Env, Format, Json, Serialized, Toml, Yaml},
+    Figment, Profile, Provider,
+};
+use serde::Deserialize;
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
 };
-use std::{fmt::Debug, path::PathBuf};
 
-use crate::{Environment, ServiceName};
+use crate::{Environment, OneOrMany, ServiceName};
 
-use super::{ConfigEnvVar, ConfigFile, ConfigFormat};
+use super::{
+    ConfigEnvVar, ConfigFile, ConfigFileFormat, ConfigFormat, ConfigVariant, CustomFormat,
+    EncryptedConfigFile,
+};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ConfigDefinition {
@@ -14,6 +21,11 @@ pub enum ConfigDefinition {
     Yaml(PathBuf),
     Json(PathBuf),
     Toml(PathBuf),
+    Dotenv(PathBuf),
+    Ron(PathBuf),
+    Ini(PathBuf),
+    Custom(CustomFormat, PathBuf),
+    Encrypted(EncryptedConfigFile),
     EnvVar(ConfigEnvVar),
 }
 
@@ -24,8 +36,10 @@ impl ConfigDefinition {
         #[builder(default, into)] path: PathBuf,
         #[builder(default, into)] file: ConfigFile,
         env: Option<Environment>,
+        variant: Option<ConfigVariant>,
         #[builder(into)] format: Option<ConfigFormat>,
         #[builder(into)] env_var: Option<ConfigEnvVar>,
+        #[builder(default)] encrypted: bool,
     ) -> ConfigDefinition {
         if let Some(env_var) = env_var {
             return env_var.into();
@@ -35,8 +49,10 @@ impl ConfigDefinition {
             return format
                 .construct()
                 .maybe_env(env)
+                .maybe_variant(variant)
                 .path(path)
                 .file(file)
+                .encrypted(encrypted)
                 .call();
         }
 
@@ -44,9 +60,123 @@ impl ConfigDefinition {
     }
 }
 
+impl ConfigDefinition {
+    /// A file definition in the given format, if the file exists.
+    pub fn with_format(format: ConfigFormat, path: PathBuf) -> Self {
+        if !path.exists() {
+            return ConfigDefinition::NotFound(path);
+        }
+
+        match format {
+            ConfigFormat::Yaml => ConfigDefinition::Yaml(path),
+            ConfigFormat::Json => ConfigDefinition::Json(path),
+            ConfigFormat::Toml => ConfigDefinition::Toml(path),
+            ConfigFormat::Dotenv => ConfigDefinition::Dotenv(path),
+            ConfigFormat::Ron => ConfigDefinition::Ron(path),
+            ConfigFormat::Ini => ConfigDefinition::Ini(path),
+            ConfigFormat::Custom(custom) => ConfigDefinition::Custom(custom, path),
+        }
+    }
+
+    /// A file definition for the given path, with the format taken from its
+    /// extension.
+    pub fn from_path(path: impl Into<PathBuf>) -> figment::Result<Self> {
+        let path = path.into();
+
+        if path.extension().is_some_and(|extension| extension == "enc") {
+            let file = EncryptedConfigFile::from_path(&path)
+                .map_err(|error| figment::Error::from(error.to_string()))?;
+
+            return Ok(match path.exists() {
+                true => ConfigDefinition::Encrypted(file),
+                false => ConfigDefinition::NotFound(path),
+            });
+        }
+
+        let format = ConfigFormat::from_path(&path).ok_or_else(|| {
+            figment::Error::from(format!(
+                "unsupported config format: {path}",
+                path = path.display()
+            ))
+        })?;
+
+        Ok(Self::with_format(format, path))
+    }
+
+    /// The files this file declares under `extends` (or `include`), resolved
+    /// relative to this file.
+    pub fn extends(&self) -> figment::Result<Vec<PathBuf>> {
+        let Some(path) = self.path() else {
+            return Ok(Vec::new());
+        };
+
+        let directives: ConfigDirectives = Figment::from(self.clone()).extract()?;
+        let base = path.parent().unwrap_or(Path::new(""));
+
+        Ok(match directives.extends {
+            None => Vec::new(),
+            Some(OneOrMany::One(extended)) => vec![base.join(extended)],
+            Some(OneOrMany::Many(extended)) => {
+                extended.iter().map(|path| base.join(path)).collect()
+            }
+        })
+    }
+
+    /// The file this definition reads from, if it's a file that exists.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            ConfigDefinition::Yaml(path)
+            | ConfigDefinition::Json(path)
+            | ConfigDefinition::Toml(path)
+            | ConfigDefinition::Dotenv(path)
+            | ConfigDefinition::Ron(path)
+            | ConfigDefinition::Ini(path)
+            | ConfigDefinition::Custom(_, path) => Some(path),
+            ConfigDefinition::Encrypted(file) => Some(file.path()),
+            ConfigDefinition::NotFound(_) | ConfigDefinition::EnvVar(_) => None,
+        }
+    }
+
+    /// Describe where the given dotted key would be read from in this
+    /// definition, e.g. a file path or the exact environment variable name.
+    pub fn describe(&self, key: &str) -> String {
+        match self {
+            ConfigDefinition::NotFound(path) => path.display().to_string(),
+            ConfigDefinition::EnvVar(env_var) => format!(
+                "{env_var}{key}",
+                key = key.to_uppercase().replace('.', "__")
+            ),
+            definition => definition
+                .path()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
 impl Provider for ConfigDefinition {
     fn metadata(&self) -> figment::Metadata {
-        Default::default()
+        match self {
+            ConfigDefinition::NotFound(path) => {
+                figment::Metadata::from("missing file", path.as_path())
+            }
+            ConfigDefinition::Yaml(path) => Yaml::file(path).metadata(),
+            ConfigDefinition::Json(path) => Json::file(path).metadata(),
+            ConfigDefinition::Toml(path) => Toml::file(path).metadata(),
+            ConfigDefinition::Dotenv(path) => file_metadata(ConfigFormat::Dotenv, path),
+            ConfigDefinition::Ron(path) => file_metadata(ConfigFormat::Ron, path),
+            ConfigDefinition::Ini(path) => file_metadata(ConfigFormat::Ini, path),
+            ConfigDefinition::Custom(custom, path) => {
+                file_metadata(ConfigFormat::Custom(*custom), path)
+            }
+            ConfigDefinition::Encrypted(file) => figment::Metadata::from(
+                format!("encrypted {format} file", format = file.format()),
+                file.path(),
+            ),
+            ConfigDefinition::EnvVar(env_var) => {
+                Env::prefixed(&env_var.to_string()).split("__").metadata()
+            }
+        }
     }
 
     fn data(
@@ -57,6 +187,19 @@ impl Provider for ConfigDefinition {
             ConfigDefinition::Yaml(path) => Yaml::file(path).data(),
             ConfigDefinition::Json(path) => Json::file(path).data(),
             ConfigDefinition::Toml(path) => Toml::file(path).data(),
+            ConfigDefinition::Dotenv(path) => file_data(ConfigFormat::Dotenv, path),
+            ConfigDefinition::Ron(path) => file_data(ConfigFormat::Ron, path),
+            ConfigDefinition::Ini(path) => file_data(ConfigFormat::Ini, path),
+            ConfigDefinition::Custom(custom, path) => {
+                file_data(ConfigFormat::Custom(*custom), path)
+            }
+            ConfigDefinition::Encrypted(file) => {
+                let values = file
+                    .decrypt()
+                    .map_err(|error| figment::Error::from(error.to_string()))?;
+
+                Serialized::defaults(values).data()
+            }
             ConfigDefinition::EnvVar(env_var) => {
                 Env::prefixed(&env_var.to_string()).split("__").data()
             }
@@ -64,6 +207,28 @@ impl Provider for ConfigDefinition {
     }
 }
 
+fn file_metadata(format: ConfigFormat, path: &Path) -> figment::Metadata {
+    figment::Metadata::from(format!("{format} file"), path)
+}
+
+/// Read a file in a format figment doesn't provide itself.
+fn file_data(
+    format: ConfigFormat,
+    path: &Path,
+) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, figment::Error> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|error| figment::Error::from(format!("{}: {error}", path.display())))?;
+
+    Ok(Profile::Default.collect(format.parse(&contents)?))
+}
+
+/// Directives a config file can declare about other config files.
+#[derive(Default, Deserialize)]
+struct ConfigDirectives {
+    #[serde(default, alias = "include")]
+    extends: Option<OneOrMany<PathBuf>>,
+}
+
 // we know we can build a source definition from a few different combinations of builder fields
 // 1. format + name and format + name + env = a file source
 // 2. name and name + env = an env var source
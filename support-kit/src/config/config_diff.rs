@@ -0,0 +1,260 @@
+use owo_colors::{OwoColorize, Stream};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use super::config_explanation::{is_redacted, REDACTED};
+
+/// One key that differs between two configurations. A missing side means
+/// the key was added or removed.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ConfigChange {
+    pub key: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// Every dotted key that differs between two configurations, in key order.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct ConfigDiff {
+    changes: Vec<ConfigChange>,
+}
+
+impl ConfigDiff {
+    pub fn between(before: &impl Serialize, after: &impl Serialize) -> serde_json::Result<Self> {
+        let before = flatten(serde_json::to_value(before)?);
+        let mut after = flatten(serde_json::to_value(after)?);
+        let mut changes = Vec::new();
+
+        for (key, before) in before {
+            match after.remove(&key) {
+                Some(after) if after == before => {}
+                after => changes.push(ConfigChange {
+                    key,
+                    before: Some(before),
+                    after,
+                }),
+            }
+        }
+
+        changes.extend(after.into_iter().map(|(key, after)| ConfigChange {
+            key,
+            before: None,
+            after: Some(after),
+        }));
+
+        changes.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Ok(Self { changes })
+    }
+
+    pub fn changes(&self) -> &[ConfigChange] {
+        &self.changes
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ConfigChange> {
+        self.changes.iter().find(|change| change.key == key)
+    }
+
+    /// Whether the given key, or anything nested under it, changed.
+    pub fn changed(&self, key: &str) -> bool {
+        self.changes.iter().any(|change| {
+            change.key == key
+                || change
+                    .key
+                    .strip_prefix(key)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Hide the values of secret keys, and of any of the given keys, while
+    /// still showing that they changed.
+    pub fn redact<T: AsRef<str>>(mut self, keys: &[T]) -> Self {
+        for change in &mut self.changes {
+            let secret =
+                is_redacted(&change.key) || keys.iter().any(|key| key.as_ref() == change.key);
+
+            if secret {
+                for value in [&mut change.before, &mut change.after]
+                    .into_iter()
+                    .flatten()
+                {
+                    *value = REDACTED.into();
+                }
+            }
+        }
+
+        self
+    }
+
+    /// A line per change, `-` removed in red, `+` added in green and `~`
+    /// changed in yellow, when the terminal supports color.
+    pub fn to_colored_string(&self) -> String {
+        self.changes
+            .iter()
+            .map(|change| match (&change.before, &change.after) {
+                (Some(before), None) => format!("- {key}: {before}", key = change.key)
+                    .if_supports_color(Stream::Stdout, |line| line.red())
+                    .to_string(),
+                (None, Some(after)) => format!("+ {key}: {after}", key = change.key)
+                    .if_supports_color(Stream::Stdout, |line| line.green())
+                    .to_string(),
+                (before, after) => format!(
+                    "~ {key}: {before} -> {after}",
+                    key = change.key,
+                    before = show(before),
+                    after = show(after)
+                )
+                .if_supports_color(Stream::Stdout, |line| line.yellow())
+                .to_string(),
+            })
+            .map(|line| format!("{line}\n"))
+            .collect()
+    }
+}
+
+fn show(value: &Option<Value>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "(unset)".to_string(),
+    }
+}
+
+impl Display for ConfigDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            writeln!(
+                f,
+                "{key}: {before} -> {after}",
+                key = change.key,
+                before = show(&change.before),
+                after = show(&change.after)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn flatten(value: Value) -> BTreeMap<String, Value> {
+    let mut leaves = BTreeMap::new();
+
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                for (nested_key, nested_value) in flatten(value) {
+                    let key = match nested_key.as_str() {
+                        "" => key.clone(),
+                        _ => format!("{key}.{nested_key}"),
+                    };
+
+                    leaves.insert(key, nested_value);
+                }
+            }
+        }
+        value => {
+            leaves.insert(String::new(), value);
+        }
+    }
+
+    leaves
+}
+
+#[test]
+fn diffing_configurations() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::{Configuration, Verbosity};
+
+    let before = Configuration::builder()
+        .verbosity(Verbosity::Info)
+        .server(("localhost", 8080))
+        .build();
+    let after = Configuration::builder()
+        .verbosity(Verbosity::Debug)
+        .server(("localhost", 8080))
+        .environment(crate::Environment::PRODUCTION)
+        .build();
+
+    let diff = ConfigDiff::between(&before, &after)?;
+
+    assert_eq!(
+        diff.changes(),
+        &[
+            ConfigChange {
+                key: "environment".into(),
+                before: None,
+                after: Some("production".into()),
+            },
+            ConfigChange {
+                key: "verbosity".into(),
+                before: Some("info".into()),
+                after: Some("debug".into()),
+            },
+        ]
+    );
+
+    assert!(!diff.changed("server"));
+    assert!(!diff.changed("verb"));
+    assert!(ConfigDiff::between(&after, &after)?.is_empty());
+
+    assert_eq!(
+        diff.to_string(),
+        "environment: (unset) -> \"production\"\nverbosity: \"info\" -> \"debug\"\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn diffing_environments() {
+    use clap::Parser;
+
+    use crate::{Args, SupportControl};
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file("support-kit.yaml", "verbosity: info")?;
+        jail.create_file(
+            "support-kit.staging.yaml",
+            "verbosity: debug\ntoken: staging-token",
+        )?;
+        jail.create_file(
+            "support-kit.production.yaml",
+            "verbosity: warn\ntoken: production-token",
+        )?;
+
+        let args = Args::try_parse_from("app".split_whitespace()).unwrap();
+        let control = SupportControl::load_configuration(&args).unwrap();
+        let diff = control.diff("staging", "production", false).unwrap();
+
+        assert!(diff.changed("verbosity"));
+        assert!(!diff.to_string().contains("production-token"));
+        assert_eq!(
+            serde_json::to_value(&diff).unwrap(),
+            serde_json::json!([
+                {
+                    "key": "environment",
+                    "before": "staging",
+                    "after": "production",
+                },
+                {
+                    "key": "token",
+                    "before": "[redacted]",
+                    "after": "[redacted]",
+                },
+                {
+                    "key": "verbosity",
+                    "before": "debug",
+                    "after": "warn",
+                },
+            ])
+        );
+        assert!(!diff.to_colored_string().contains("staging-token"));
+
+        Ok(())
+    });
+}
@@ -0,0 +1,504 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::Value;
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
+
+use crate::ConfigEncryptionError;
+
+use super::{config_env_var::env_prefix, ConfigFile, ConfigFileFormat, ConfigFormat};
+
+/// Marks an encrypted value, followed by the nonce and ciphertext.
+const ENCRYPTED_PREFIX: &str = "ENC[aes256gcm,";
+
+/// Top level keys that are read before decryption, so they stay readable.
+const PLAIN_KEYS: &[&str] = &["$schema", "extends", "include"];
+
+/// The key config files are encrypted with.
+///
+/// It's looked up from `<NAME>_CONFIG_KEY`, holding the key itself, then
+/// from a file named by `<NAME>_CONFIG_KEY_FILE`, and finally from
+/// `config.key` in the service's user config directory.
+#[derive(Clone)]
+pub struct ConfigKey(Key<Aes256Gcm>);
+
+impl ConfigKey {
+    pub fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(OsRng))
+    }
+
+    pub fn from_base64(encoded: &str, source: &str) -> Result<Self, ConfigEncryptionError> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .ok()
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or_else(|| ConfigEncryptionError::InvalidKey(source.to_string()))?;
+
+        Ok(Self(*Key::<Aes256Gcm>::from_slice(&bytes)))
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.0)
+    }
+
+    /// Find the key for the given config file name.
+    pub fn locate(name: &ConfigFile) -> Result<Self, ConfigEncryptionError> {
+        let variable = Self::variable(name);
+        let file_variable = format!("{variable}_FILE");
+
+        if let Ok(encoded) = std::env::var(&variable) {
+            return Self::from_base64(&encoded, &variable);
+        }
+
+        let path = match std::env::var(&file_variable) {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => Self::fallback_path(name).ok_or_else(|| {
+                ConfigEncryptionError::MissingKey(format!("{variable} and {file_variable}"))
+            })?,
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(encoded) => Self::from_base64(&encoded, &path.display().to_string()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                Err(ConfigEncryptionError::MissingKey(format!(
+                    "{variable}, {file_variable} and {path}",
+                    path = path.display()
+                )))
+            }
+            Err(error) => Err(ConfigEncryptionError::IoError(
+                path.display().to_string(),
+                error,
+            )),
+        }
+    }
+
+    /// The environment variable the key can be set in.
+    pub fn variable(name: &ConfigFile) -> String {
+        let prefix = env_prefix().name(name.clone()).call();
+
+        format!("{name}_CONFIG_KEY", name = prefix.trim_end_matches('_'))
+    }
+
+    /// Where the key is kept when it isn't set in the environment.
+    pub fn fallback_path(name: &ConfigFile) -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(name.to_string()).join("config.key"))
+    }
+
+    /// Write a new key to the fallback path, unless there's one already.
+    pub fn create(name: &ConfigFile) -> Result<PathBuf, ConfigEncryptionError> {
+        let path = Self::fallback_path(name)
+            .ok_or_else(|| ConfigEncryptionError::MissingKey("a config directory".into()))?;
+        let io_error = |error| ConfigEncryptionError::IoError(path.display().to_string(), error);
+
+        if path.exists() {
+            return Ok(path);
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error)?;
+        }
+
+        write_private(&path, Self::generate().to_base64().as_bytes()).map_err(io_error)?;
+
+        Ok(path)
+    }
+
+    fn encrypt_leaf(&self, key: &str, value: &Value) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let plaintext = serde_json::to_vec(value).unwrap_or_default();
+        let ciphertext = Aes256Gcm::new(&self.0)
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: key.as_bytes(),
+                },
+            )
+            .expect("encrypting in memory");
+
+        format!(
+            "{ENCRYPTED_PREFIX}{nonce},{ciphertext}]",
+            nonce = STANDARD.encode(nonce),
+            ciphertext = STANDARD.encode(ciphertext)
+        )
+    }
+
+    fn decrypt_leaf(&self, key: &str, encrypted: &str) -> Result<Value, ConfigEncryptionError> {
+        let failed = || ConfigEncryptionError::DecryptionFailed(key.to_string());
+        let (nonce, ciphertext) = encrypted
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .and_then(|rest| rest.strip_suffix(']'))
+            .and_then(|rest| rest.split_once(','))
+            .ok_or_else(failed)?;
+        let nonce = STANDARD.decode(nonce).map_err(|_| failed())?;
+        let ciphertext = STANDARD.decode(ciphertext).map_err(|_| failed())?;
+
+        if nonce.len() != 12 {
+            return Err(failed());
+        }
+
+        let plaintext = Aes256Gcm::new(&self.0)
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|_| failed())?;
+
+        serde_json::from_slice(&plaintext).map_err(|_| failed())
+    }
+
+    /// Encrypt every value, leaving keys readable. A value that's unchanged
+    /// from the previous encryption keeps its ciphertext, so diffs only
+    /// show what changed.
+    pub fn encrypt(&self, value: &Value, previous: Option<&Value>) -> Value {
+        self.encrypt_at("", value, previous)
+    }
+
+    fn encrypt_at(&self, key: &str, value: &Value, previous: Option<&Value>) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(name, nested)| {
+                        let nested = if key.is_empty() && PLAIN_KEYS.contains(&name.as_str()) {
+                            nested.clone()
+                        } else {
+                            self.encrypt_at(
+                                &join(key, name),
+                                nested,
+                                previous.and_then(|previous| previous.get(name)),
+                            )
+                        };
+
+                        (name.clone(), nested)
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| {
+                        self.encrypt_at(
+                            &join(key, &index.to_string()),
+                            item,
+                            previous.and_then(|previous| previous.get(index)),
+                        )
+                    })
+                    .collect(),
+            ),
+            Value::Null => Value::Null,
+            leaf => match previous {
+                Some(Value::String(encrypted))
+                    if self.decrypt_leaf(key, encrypted).ok().as_ref() == Some(leaf) =>
+                {
+                    Value::String(encrypted.clone())
+                }
+                _ => Value::String(self.encrypt_leaf(key, leaf)),
+            },
+        }
+    }
+
+    /// Decrypt every encrypted value. Values that aren't encrypted are kept.
+    pub fn decrypt(&self, value: &Value) -> Result<Value, ConfigEncryptionError> {
+        self.decrypt_at("", value)
+    }
+
+    fn decrypt_at(&self, key: &str, value: &Value) -> Result<Value, ConfigEncryptionError> {
+        Ok(match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(name, nested)| {
+                        Ok((name.clone(), self.decrypt_at(&join(key, name), nested)?))
+                    })
+                    .collect::<Result<_, ConfigEncryptionError>>()?,
+            ),
+            Value::Array(items) => Value::Array(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(index, item)| self.decrypt_at(&join(key, &index.to_string()), item))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::String(encrypted) if encrypted.starts_with(ENCRYPTED_PREFIX) => {
+                self.decrypt_leaf(key, encrypted)?
+            }
+            value => value.clone(),
+        })
+    }
+}
+
+impl Debug for ConfigKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ConfigKey([redacted])")
+    }
+}
+
+/// A config file with encrypted values, named like a plain config file
+/// with `.enc` added, e.g. `support-kit.production.yaml.enc`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncryptedConfigFile {
+    path: PathBuf,
+    format: ConfigFormat,
+    name: ConfigFile,
+}
+
+impl EncryptedConfigFile {
+    /// An encrypted file in the given format, whose key is found by name.
+    pub fn new(
+        path: impl Into<PathBuf>,
+        format: ConfigFormat,
+        name: impl Into<ConfigFile>,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            name: name.into(),
+        }
+    }
+
+    /// Take the format from the extension before `.enc`, and the key name
+    /// from the start of the file name (or the directory, for `config.*`).
+    pub fn from_path(path: impl Into<PathBuf>) -> Result<Self, ConfigEncryptionError> {
+        let path = path.into();
+        let not_encrypted = || ConfigEncryptionError::NotEncrypted(path.display().to_string());
+        let plain = path
+            .to_str()
+            .and_then(|path| path.strip_suffix(".enc"))
+            .map(Path::new)
+            .ok_or_else(not_encrypted)?;
+        let format = ConfigFormat::from_path(plain).ok_or_else(not_encrypted)?;
+        let file_name = plain
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split('.').next())
+            .ok_or_else(not_encrypted)?;
+        let name = match file_name {
+            "config" => plain
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .and_then(|name| name.to_str())
+                .unwrap_or(file_name),
+            name => name,
+        };
+
+        Ok(Self::new(path.clone(), format, name))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn format(&self) -> ConfigFormat {
+        self.format
+    }
+
+    pub fn key(&self) -> Result<ConfigKey, ConfigEncryptionError> {
+        ConfigKey::locate(&self.name)
+    }
+
+    /// The file as written, with values still encrypted.
+    pub fn read_encrypted(&self) -> Result<Value, ConfigEncryptionError> {
+        let contents = std::fs::read_to_string(&self.path).map_err(|error| {
+            ConfigEncryptionError::IoError(self.path.display().to_string(), error)
+        })?;
+
+        parse(self.format, &self.path, &contents)
+    }
+
+    pub fn decrypt(&self) -> Result<Value, ConfigEncryptionError> {
+        self.key()?.decrypt(&self.read_encrypted()?)
+    }
+
+    /// Decrypt into the file's own format.
+    pub fn decrypt_to_string(&self) -> Result<String, ConfigEncryptionError> {
+        serialize(self.format, &self.decrypt()?)
+    }
+
+    /// Encrypt and write the given values, keeping the ciphertext of any
+    /// value that hasn't changed.
+    pub fn write(&self, values: &Value) -> Result<(), ConfigEncryptionError> {
+        let previous = self
+            .path
+            .exists()
+            .then(|| self.read_encrypted())
+            .transpose()?;
+        let encrypted = self.key()?.encrypt(values, previous.as_ref());
+
+        std::fs::write(&self.path, serialize(self.format, &encrypted)?)
+            .map_err(|error| ConfigEncryptionError::IoError(self.path.display().to_string(), error))
+    }
+
+    /// Encrypt a plain config file next to it, adding `.enc` to its name.
+    pub fn encrypt_file(plain: &Path) -> Result<Self, ConfigEncryptionError> {
+        let file = Self::from_path(format!("{plain}.enc", plain = plain.display()))?;
+        let contents = std::fs::read_to_string(plain)
+            .map_err(|error| ConfigEncryptionError::IoError(plain.display().to_string(), error))?;
+
+        file.write(&parse(file.format, plain, &contents)?)?;
+
+        Ok(file)
+    }
+
+    /// Decrypt to a private temporary file, open it in `$VISUAL` or
+    /// `$EDITOR`, then encrypt whatever was saved.
+    pub fn edit(&self) -> Result<(), ConfigEncryptionError> {
+        let values = match self.path.exists() {
+            true => self.decrypt()?,
+            false => Value::Object(Default::default()),
+        };
+        let dir = std::env::temp_dir().join(format!("config-edit-{id}", id = uuid::Uuid::new_v4()));
+        let plain = dir.join(format!(
+            "{name}.{format}",
+            name = self.name,
+            format = self.format
+        ));
+        let io_error = |error| ConfigEncryptionError::IoError(plain.display().to_string(), error);
+
+        std::fs::create_dir_all(&dir).map_err(io_error)?;
+
+        let edited = write_private(&plain, serialize(self.format, &values)?.as_bytes())
+            .map_err(io_error)
+            .and_then(|_| run_editor(&plain))
+            .and_then(|_| std::fs::read_to_string(&plain).map_err(io_error))
+            .and_then(|contents| parse(self.format, &plain, &contents));
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        self.write(&edited?)
+    }
+}
+
+fn join(key: &str, name: &str) -> String {
+    match key {
+        "" => name.to_string(),
+        _ => format!("{key}.{name}"),
+    }
+}
+
+fn parse(
+    format: ConfigFormat,
+    path: &Path,
+    contents: &str,
+) -> Result<Value, ConfigEncryptionError> {
+    format
+        .parse(contents)
+        .map_err(|error| error.to_string())
+        .and_then(|dict| serde_json::to_value(dict).map_err(|error| error.to_string()))
+        .map_err(|error| ConfigEncryptionError::ParseError(path.display().to_string(), error))
+}
+
+fn serialize(format: ConfigFormat, value: &Value) -> Result<String, ConfigEncryptionError> {
+    let serialized = match format {
+        ConfigFormat::Yaml => serde_yaml::to_string(value).map_err(|error| error.to_string()),
+        ConfigFormat::Json => {
+            serde_json::to_string_pretty(value).map_err(|error| error.to_string())
+        }
+        ConfigFormat::Toml => toml::to_string_pretty(value).map_err(|error| error.to_string()),
+        format => return Err(ConfigEncryptionError::UnsupportedFormat(format.to_string())),
+    };
+
+    serialized.map_err(|error| ConfigEncryptionError::ParseError(format.to_string(), error))
+}
+
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    std::io::Write::write_all(&mut options.open(path)?, contents)
+}
+
+fn run_editor(path: &Path) -> Result<(), ConfigEncryptionError> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| ConfigEncryptionError::EditorFailed("no editor set".into()))?;
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|error| ConfigEncryptionError::EditorFailed(format!("{editor}: {error}")))?;
+
+    match status.success() {
+        true => Ok(()),
+        false => Err(ConfigEncryptionError::EditorFailed(format!(
+            "{editor} exited with {status}"
+        ))),
+    }
+}
+
+#[test]
+fn encrypting_config_files() {
+    use clap::Parser;
+    use secrecy::ExposeSecret;
+
+    use crate::{Args, SupportControl};
+
+    figment::Jail::expect_with(|jail| {
+        let key = ConfigKey::generate();
+
+        jail.set_env("SUPPORT_KIT_CONFIG_KEY", key.to_base64());
+        jail.create_file("support-kit.yaml", "environment: production")?;
+        jail.create_file(
+            "support-kit.production.yaml",
+            "secret: hunter2\nserver:\n  port: 8080",
+        )?;
+
+        let file =
+            EncryptedConfigFile::encrypt_file(Path::new("support-kit.production.yaml")).unwrap();
+        let encrypted = std::fs::read_to_string(file.path()).unwrap();
+
+        assert_eq!(file.path(), Path::new("support-kit.production.yaml.enc"));
+        assert!(encrypted.contains("secret: ENC[aes256gcm,"));
+        assert!(!encrypted.contains("hunter2"));
+
+        std::fs::remove_file("support-kit.production.yaml").unwrap();
+
+        let args = Args::try_parse_from("app".split_whitespace()).unwrap();
+        let control = SupportControl::load_configuration(&args).unwrap();
+
+        assert_eq!(control.config.secret.expose_secret(), "hunter2");
+        assert_eq!(control.config.server.port.as_u16(), Some(8080));
+        assert_eq!(
+            control.explain().unwrap().get("server.port").unwrap().value,
+            "[redacted]"
+        );
+
+        file.write(&file.decrypt().unwrap()).unwrap();
+
+        assert_eq!(std::fs::read_to_string(file.path()).unwrap(), encrypted);
+
+        jail.set_env("EDITOR", "sed -i s/8080/9090/");
+        file.edit().unwrap();
+
+        let edited = std::fs::read_to_string(file.path()).unwrap();
+
+        assert_eq!(file.decrypt().unwrap()["server"]["port"], 9090);
+        assert_eq!(
+            edited.lines().find(|line| line.starts_with("secret")),
+            encrypted.lines().find(|line| line.starts_with("secret"))
+        );
+
+        jail.set_env("SUPPORT_KIT_CONFIG_KEY", ConfigKey::generate().to_base64());
+
+        assert_eq!(
+            file.decrypt().unwrap_err().to_string(),
+            "unable to decrypt `secret`, is this the right key?"
+        );
+
+        Ok(())
+    });
+}
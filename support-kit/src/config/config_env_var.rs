@@ -20,7 +20,7 @@ impl Display for ConfigEnvVar {
             "{prefix}",
             prefix = env_prefix()
                 .name(self.file.clone())
-                .maybe_env(self.env)
+                .maybe_env(self.env.clone())
                 .call()
         )
     }
@@ -33,7 +33,7 @@ impl Debug for ConfigEnvVar {
             "{prefix:?}",
             prefix = env_prefix()
                 .name(self.file.clone())
-                .maybe_env(self.env)
+                .maybe_env(self.env.clone())
                 .call()
         )
     }
@@ -48,7 +48,7 @@ pub fn env_prefix(#[builder(into)] name: String, env: Option<Environment>) -> St
     match env {
         Some(env) => format!(
             "{name}__{config_env}__",
-            config_env = env.to_string().to_case(Case::UpperSnake)
+            config_env = env.name().to_uppercase().replace('-', "_")
         ),
         None => format!("{name}__", name = name.to_case(Case::UpperSnake)),
     }
@@ -89,3 +89,26 @@ where
         }
     }
 }
+
+#[test]
+fn env_prefixes() -> Result<(), Box<dyn std::error::Error>> {
+    let expectations = [
+        (None, "SUPPORT_KIT__"),
+        (Some(Environment::PRODUCTION), "SUPPORT_KIT__PRODUCTION__"),
+        (Some(Environment::new("staging")?), "SUPPORT_KIT__STAGING__"),
+        (
+            Some(Environment::new("preview-123")?),
+            "SUPPORT_KIT__PREVIEW_123__",
+        ),
+        (Some(Environment::new("qa2")?), "SUPPORT_KIT__QA2__"),
+    ];
+
+    for (env, expected) in expectations {
+        assert_eq!(
+            env_prefix().name("support-kit").maybe_env(env).call(),
+            expected
+        );
+    }
+
+    Ok(())
+}
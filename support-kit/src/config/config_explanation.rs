@@ -0,0 +1,240 @@
+use figment::{
+    value::{Dict, Value},
+    Figment, Provider,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+use super::{ConfigDefinition, ConfigOrigin};
+
+/// Keys whose values are never shown when explaining configuration. Values
+/// from encrypted files are never shown either.
+const REDACTED_KEYS: &[&str] = &["secret", "token", "password"];
+pub(crate) const REDACTED: &str = "[redacted]";
+
+/// A single resolved configuration key, along with the layer that set it and
+/// every earlier layer it overrode (most recent first).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigExplanationEntry {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub source: String,
+    pub overridden: Vec<String>,
+}
+
+/// Every resolved configuration key and where it came from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigExplanation {
+    entries: Vec<ConfigExplanationEntry>,
+}
+
+impl ConfigExplanation {
+    /// Walk the given layers in merge order and attribute each resolved key
+    /// to the last layer that provided it.
+    pub fn from_origins(origins: &[ConfigOrigin]) -> crate::Result<Self> {
+        let mut providers: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut encrypted = BTreeSet::new();
+
+        for origin in origins {
+            let is_encrypted = matches!(
+                origin,
+                ConfigOrigin::Definition(ConfigDefinition::Encrypted(_))
+            );
+
+            for dict in origin.data()?.values() {
+                for (key, _) in flatten(dict) {
+                    let source = origin.describe(&key);
+
+                    match is_encrypted {
+                        true => encrypted.insert(key.clone()),
+                        false => encrypted.remove(&key),
+                    };
+
+                    providers.entry(key).or_default().push(source);
+                }
+            }
+        }
+
+        let merged: Dict = origins
+            .iter()
+            .fold(Figment::new(), |figment, origin| figment.merge(origin))
+            .extract()?;
+
+        let entries = flatten(&merged)
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let mut sources = providers.remove(&key)?;
+                let source = sources.pop()?;
+                sources.reverse();
+
+                let value = match encrypted.contains(&key) {
+                    true => serde_json::Value::String(REDACTED.to_string()),
+                    false => redact(&key, value),
+                };
+
+                Some(ConfigExplanationEntry {
+                    value,
+                    key,
+                    source,
+                    overridden: sources,
+                })
+            })
+            .collect();
+
+        Ok(Self { entries })
+    }
+
+    pub fn entries(&self) -> &[ConfigExplanationEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ConfigExplanationEntry> {
+        self.entries.iter().find(|entry| entry.key == key)
+    }
+
+    /// Every key whose value is hidden.
+    pub fn redacted_keys(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.value == REDACTED)
+            .map(|entry| entry.key.clone())
+            .collect()
+    }
+}
+
+impl Display for ConfigExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{key} = {value}", key = entry.key, value = entry.value)?;
+            writeln!(f, "    from {source}", source = entry.source)?;
+
+            if !entry.overridden.is_empty() {
+                writeln!(
+                    f,
+                    "    overrides {sources}",
+                    sources = entry.overridden.join(", ")
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn flatten(dict: &Dict) -> Vec<(String, Value)> {
+    let mut leaves = Vec::new();
+
+    for (key, value) in dict {
+        match value {
+            Value::Dict(_, nested) if !nested.is_empty() => {
+                for (nested_key, nested_value) in flatten(nested) {
+                    leaves.push((format!("{key}.{nested_key}"), nested_value));
+                }
+            }
+            _ => leaves.push((key.clone(), value.clone())),
+        }
+    }
+
+    leaves
+}
+
+/// Whether the value for a dotted key is never shown.
+pub(crate) fn is_redacted(key: &str) -> bool {
+    let name = key.rsplit('.').next().unwrap_or(key);
+
+    REDACTED_KEYS.contains(&name)
+}
+
+fn redact(key: &str, value: Value) -> serde_json::Value {
+    if is_redacted(key) {
+        serde_json::Value::String(REDACTED.to_string())
+    } else {
+        serde_json::to_value(value).unwrap_or_default()
+    }
+}
+
+#[test]
+fn explaining_config_sources() {
+    use clap::Parser;
+
+    use crate::{Args, SupportControl};
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file(
+            "support-kit.yaml",
+            r#"
+            environment: production
+            secret: hunter2
+            verbosity: info
+        "#,
+        )?;
+
+        jail.create_file(
+            "support-kit.production.yaml",
+            r#"
+            verbosity: warn
+        "#,
+        )?;
+
+        jail.set_env("SUPPORT_KIT__PRODUCTION__VERBOSITY", "trace");
+
+        let args = Args::try_parse_from("app".split_whitespace()).unwrap();
+        let control = SupportControl::load_configuration(&args).unwrap();
+        let explanation = control.explain().unwrap();
+
+        assert_eq!(
+            explanation.get("verbosity"),
+            Some(&ConfigExplanationEntry {
+                key: "verbosity".into(),
+                value: "trace".into(),
+                source: "SUPPORT_KIT__PRODUCTION__VERBOSITY".into(),
+                overridden: vec![
+                    "support-kit.production.yaml".into(),
+                    "support-kit.yaml".into(),
+                    "defaults".into(),
+                ],
+            })
+        );
+
+        assert_eq!(
+            explanation.get("environment").map(|entry| &entry.source),
+            Some(&"support-kit.yaml".to_string())
+        );
+
+        assert_eq!(
+            explanation.get("color").map(|entry| &entry.source),
+            Some(&"defaults".to_string())
+        );
+
+        let args = Args::try_parse_from("app --color never -vv".split_whitespace()).unwrap();
+        let explanation = SupportControl::load_configuration(&args)
+            .unwrap()
+            .explain()
+            .unwrap();
+
+        assert_eq!(
+            explanation.get("color"),
+            Some(&ConfigExplanationEntry {
+                key: "color".into(),
+                value: "never".into(),
+                source: "command line arguments".into(),
+                overridden: vec!["defaults".into()],
+            })
+        );
+        assert_eq!(
+            explanation
+                .get("verbosity")
+                .map(|entry| &entry.overridden[2..]),
+            Some(&["command line arguments".to_string(), "defaults".to_string()][..])
+        );
+
+        assert_eq!(
+            explanation.get("secret").map(|entry| &entry.value),
+            Some(&serde_json::Value::from(REDACTED))
+        );
+
+        assert!(!explanation.to_string().contains("hunter2"));
+
+        Ok(())
+    });
+}
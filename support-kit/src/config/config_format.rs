@@ -1,17 +1,68 @@
-use std::path::PathBuf;
-
-use strum::{EnumString, VariantArray};
+use figment::{
+    providers::{Format, Json, Serialized, Toml, Yaml},
+    value::{Dict, Value},
+    Figment,
+};
+use std::{
+    fmt::{Debug, Display},
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
 
 use crate::Environment;
 
-use super::{config_definition::ConfigDefinition, ConfigFile};
+use super::{config_definition::ConfigDefinition, ConfigFile, ConfigVariant, EncryptedConfigFile};
+
+/// A file format config can be read from. Implement this for a format of
+/// your own and [register](ConfigFormat::register) it to have config sources
+/// look for files with its extension.
+pub trait ConfigFileFormat: Debug + Send + Sync {
+    /// The file extension, without a leading dot.
+    fn extension(&self) -> &str;
+
+    /// Parse the contents of a file into config values.
+    fn parse(&self, contents: &str) -> figment::Result<Dict>;
+
+    /// The contents of a file that sets nothing.
+    fn empty_file_contents(&self) -> &str {
+        ""
+    }
+}
+
+static REGISTERED: RwLock<Vec<CustomFormat>> = RwLock::new(Vec::new());
+
+/// A format registered with [`ConfigFormat::register`].
+#[derive(Clone, Copy)]
+pub struct CustomFormat(&'static dyn ConfigFileFormat);
+
+impl CustomFormat {
+    pub fn format(&self) -> &'static dyn ConfigFileFormat {
+        self.0
+    }
+}
+
+impl Debug for CustomFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
 
-#[derive(Clone, Copy, Debug, strum::Display, EnumString, VariantArray)]
-#[strum(serialize_all = "snake_case")]
+impl PartialEq for CustomFormat {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.extension() == other.0.extension()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ConfigFormat {
     Yaml,
     Json,
     Toml,
+    /// `KEY=value` lines, nested with `__` like environment variables.
+    Dotenv,
+    Ron,
+    Ini,
+    Custom(CustomFormat),
 }
 
 #[bon::bon]
@@ -22,34 +73,217 @@ impl ConfigFormat {
         #[builder(into)] path: PathBuf,
         #[builder(default, into)] file: ConfigFile,
         env: Option<Environment>,
+        variant: Option<ConfigVariant>,
+        #[builder(default)] encrypted: bool,
     ) -> ConfigDefinition {
         let ext = format!(
-            "{ext}{kind}",
-            ext = env.map(|env| format!("{env}.")).unwrap_or_default(),
+            "{env}{variant}{kind}",
+            env = env.map(|env| format!("{env}.")).unwrap_or_default(),
+            variant = variant
+                .map(|variant| format!("{variant}."))
+                .unwrap_or_default(),
             kind = self
         );
 
-        let file = path.join(format!("{file}.{ext}"));
-        if file.exists() {
-            match self {
-                Self::Yaml => ConfigDefinition::Yaml(file),
-                Self::Json => ConfigDefinition::Json(file),
-                Self::Toml => ConfigDefinition::Toml(file),
-            }
-        } else {
-            ConfigDefinition::NotFound(file)
+        if encrypted {
+            let path = path.join(format!("{file}.{ext}.enc"));
+
+            return match path.exists() {
+                true => ConfigDefinition::Encrypted(EncryptedConfigFile::new(path, *self, file)),
+                false => ConfigDefinition::NotFound(path),
+            };
         }
+
+        ConfigDefinition::with_format(*self, path.join(format!("{file}.{ext}")))
     }
+}
+
+impl ConfigFormat {
+    pub(crate) const BUILT_IN: [Self; 6] = [
+        Self::Yaml,
+        Self::Json,
+        Self::Toml,
+        Self::Dotenv,
+        Self::Ron,
+        Self::Ini,
+    ];
+
+    /// Look for config files in another format, alongside the built in ones.
+    /// A format registered later replaces an earlier one with the same
+    /// extension, but built in formats always win.
+    pub fn register(format: impl ConfigFileFormat + 'static) {
+        let format = CustomFormat(Box::leak(Box::new(format)));
 
+        if let Ok(mut registered) = REGISTERED.write() {
+            registered.retain(|existing| existing != &format);
+            registered.push(format);
+        }
+    }
+
+    /// Stop looking for files with a registered format's extension.
+    pub fn unregister(extension: &str) {
+        if let Ok(mut registered) = REGISTERED.write() {
+            registered.retain(|existing| existing.0.extension() != extension);
+        }
+    }
+
+    /// The format of a file, going by its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "yml" => Some(Self::Yaml),
+            extension => Self::all()
+                .into_iter()
+                .find(|format| format.extension() == extension),
+        }
+    }
+
+    /// The built in formats, then every registered format.
     pub fn all() -> Vec<ConfigFormat> {
-        ConfigFormat::VARIANTS.to_vec()
+        let mut formats = Self::BUILT_IN.to_vec();
+
+        if let Ok(registered) = REGISTERED.read() {
+            formats.extend(registered.iter().copied().map(Self::Custom));
+        }
+
+        formats
+    }
+}
+
+impl ConfigFileFormat for ConfigFormat {
+    fn extension(&self) -> &str {
+        match self {
+            Self::Yaml => "yaml",
+            Self::Json => "json",
+            Self::Toml => "toml",
+            Self::Dotenv => "env",
+            Self::Ron => "ron",
+            Self::Ini => "ini",
+            Self::Custom(custom) => custom.0.extension(),
+        }
+    }
+
+    fn parse(&self, contents: &str) -> figment::Result<Dict> {
+        match self {
+            Self::Yaml => Yaml::from_str(contents).map_err(|error| error.to_string().into()),
+            Self::Json => Json::from_str(contents).map_err(|error| error.to_string().into()),
+            Self::Toml => Toml::from_str(contents).map_err(|error| error.to_string().into()),
+            Self::Dotenv => parse_dotenv(contents),
+            Self::Ron => ron::from_str::<ron::Value>(contents)
+                .map_err(|error| error.to_string())?
+                .into_rust()
+                .map_err(|error| error.to_string().into()),
+            Self::Ini => parse_ini(contents),
+            Self::Custom(custom) => custom.0.parse(contents),
+        }
     }
 
-    pub fn empty_file_contents(&self) -> &'static str {
+    fn empty_file_contents(&self) -> &str {
         match self {
-            Self::Json => "{}",
-            Self::Yaml => "",
-            Self::Toml => "",
+            Self::Json | Self::Ron => "{}",
+            Self::Yaml | Self::Toml | Self::Dotenv | Self::Ini => "",
+            Self::Custom(custom) => custom.0.empty_file_contents(),
         }
     }
 }
+
+impl Display for ConfigFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+/// Nest dotted keys and parse each value the way environment variables are.
+fn nest<'a>(pairs: impl IntoIterator<Item = (String, &'a str)>) -> figment::Result<Dict> {
+    pairs
+        .into_iter()
+        .fold(Figment::new(), |figment, (key, value)| {
+            figment.merge(Serialized::default(
+                &key,
+                value
+                    .parse::<Value>()
+                    .unwrap_or_else(|never| match never {}),
+            ))
+        })
+        .extract()
+}
+
+fn parse_dotenv(contents: &str) -> figment::Result<Dict> {
+    let mut pairs = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line.split_once('=').ok_or_else(|| {
+            figment::Error::from(format!(
+                "line {line}: expected `KEY=value`",
+                line = index + 1
+            ))
+        })?;
+        let value = value.trim();
+        let value = ['"', '\'']
+            .into_iter()
+            .find_map(|quote| {
+                value
+                    .strip_prefix(quote)
+                    .and_then(|value| value.strip_suffix(quote))
+            })
+            .unwrap_or(value);
+
+        pairs.push((key.trim().to_lowercase().replace("__", "."), value));
+    }
+
+    nest(pairs)
+}
+
+fn parse_ini(contents: &str) -> figment::Result<Dict> {
+    let ini = ini::Ini::load_from_str(contents).map_err(|error| error.to_string())?;
+
+    nest(ini.iter().flat_map(|(section, properties)| {
+        properties.iter().map(move |(key, value)| {
+            let key = match section {
+                Some(section) => format!("{section}.{key}"),
+                None => key.to_string(),
+            };
+
+            (key, value)
+        })
+    }))
+}
+
+#[test]
+fn parsing_formats() -> Result<(), Box<dyn std::error::Error>> {
+    use crate::Configuration;
+
+    let sources = [
+        (
+            ConfigFormat::Dotenv,
+            "# comment\nVERBOSITY=debug\nexport SERVER__HOST=\"0.0.0.0\"\nSERVER__PORT=8080",
+        ),
+        (
+            ConfigFormat::Ron,
+            r#"(verbosity: "debug", server: (host: "0.0.0.0", port: 8080))"#,
+        ),
+        (
+            ConfigFormat::Ini,
+            "verbosity = debug\n\n[server]\nhost = 0.0.0.0\nport = 8080",
+        ),
+    ];
+
+    for (format, contents) in sources {
+        let config: Configuration =
+            Figment::from(Serialized::defaults(format.parse(contents)?)).extract()?;
+
+        assert_eq!(config.verbosity, crate::Verbosity::Debug, "{format}");
+        assert_eq!(config.server.host.to_string(), "0.0.0.0", "{format}");
+        assert_eq!(config.server.port.as_u16(), Some(8080), "{format}");
+    }
+
+    assert!(ConfigFormat::Dotenv.parse("VERBOSITY").is_err());
+
+    Ok(())
+}
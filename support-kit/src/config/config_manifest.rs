@@ -1,5 +1,5 @@
 use figment::{Figment, Provider};
-use std::fmt::Debug;
+use std::{fmt::Debug, path::PathBuf};
 
 use super::ConfigDefinition;
 
@@ -13,6 +13,10 @@ impl ConfigManifest {
         self.definitions.extend(source.definitions);
     }
 
+    pub fn definitions(&self) -> &[ConfigDefinition] {
+        &self.definitions
+    }
+
     pub fn missing(&self) -> Self {
         let definitions = self
             .definitions
@@ -34,21 +38,88 @@ impl ConfigManifest {
 
         Self::builder().definitions(definitions).build()
     }
+
+    /// Pull in every file the definitions extend, recursively, placing each
+    /// one before the file that extends it so the extending file wins.
+    pub fn resolve_extends(self) -> figment::Result<Self> {
+        let mut definitions = Vec::new();
+
+        for definition in self.definitions {
+            extend(definition, &mut Vec::new(), &mut definitions)?;
+        }
+
+        Ok(Self::builder().definitions(definitions).build())
+    }
+
+    /// Merge every definition in order, keeping the metadata of each one so
+    /// values can be traced back to the definition that provided them.
+    pub fn figment(&self) -> Figment {
+        let mut figment = Figment::new();
+        for source in &self.definitions {
+            figment = figment.merge(source);
+        }
+
+        figment
+    }
+}
+
+fn extend(
+    definition: ConfigDefinition,
+    chain: &mut Vec<(PathBuf, PathBuf)>,
+    definitions: &mut Vec<ConfigDefinition>,
+) -> figment::Result<()> {
+    if let Some(path) = definition.path() {
+        let canonical = path
+            .canonicalize()
+            .map_err(|error| figment::Error::from(format!("{}: {error}", path.display())))?;
+
+        if chain.iter().any(|(seen, _)| seen == &canonical) {
+            let cycle = chain
+                .iter()
+                .map(|(_, path)| path.display().to_string())
+                .chain([path.display().to_string()])
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            return Err(figment::Error::from(format!(
+                "config file extends itself: {cycle}"
+            )));
+        }
+
+        chain.push((canonical, path.to_path_buf()));
+
+        for extended in definition.extends()? {
+            let extended = ConfigDefinition::from_path(extended)?;
+
+            if let ConfigDefinition::NotFound(missing) = &extended {
+                return Err(figment::Error::from(format!(
+                    "missing config file {missing}, extended by {path}",
+                    missing = missing.display(),
+                    path = path.display()
+                )));
+            }
+
+            extend(extended, chain, definitions)?;
+        }
+
+        chain.pop();
+    }
+
+    if !definitions.contains(&definition) {
+        definitions.push(definition);
+    }
+
+    Ok(())
 }
 
 impl Provider for ConfigManifest {
     fn metadata(&self) -> figment::Metadata {
-        Default::default()
+        figment::Metadata::named("config manifest")
     }
 
     fn data(
         &self,
     ) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, figment::Error> {
-        let mut figment = Figment::new();
-        for source in &self.definitions {
-            figment = figment.merge(source);
-        }
-
-        Ok(figment.data()?)
+        Ok(self.figment().data()?)
     }
 }
@@ -0,0 +1,82 @@
+use figment::{Metadata, Provider};
+
+use crate::Configuration;
+
+use super::{ConfigDefinition, ConfigOverrides, ConfigSection};
+
+/// One layer of the merged configuration, in the order it is applied.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigOrigin {
+    /// The configuration the control started from: defaults, along with
+    /// whatever the arguments set.
+    Defaults(Box<Configuration>),
+    /// Only the values set by flags that were passed.
+    Arguments(ConfigOverrides),
+    /// Defaults for a registered application section.
+    Section(ConfigSection),
+    /// A file or environment variable source found in the manifest.
+    Definition(ConfigDefinition),
+    /// Values set with `--set` and `--set-json`.
+    Overrides(ConfigOverrides),
+}
+
+impl ConfigOrigin {
+    /// Describe where the given dotted key comes from in this layer.
+    pub fn describe(&self, key: &str) -> String {
+        match self {
+            Self::Defaults(_) => "defaults".to_string(),
+            Self::Arguments(_) => "command line arguments".to_string(),
+            Self::Section(section) => format!("{key} section defaults", key = section.key()),
+            Self::Definition(definition) => definition.describe(key),
+            Self::Overrides(_) => "command line".to_string(),
+        }
+    }
+}
+
+impl Provider for ConfigOrigin {
+    fn metadata(&self) -> Metadata {
+        match self {
+            Self::Defaults(_) => Metadata::named("defaults"),
+            Self::Arguments(_) => Metadata::named("command line arguments"),
+            Self::Section(section) => section.metadata(),
+            Self::Definition(definition) => definition.metadata(),
+            Self::Overrides(overrides) => overrides.metadata(),
+        }
+    }
+
+    fn data(
+        &self,
+    ) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, figment::Error> {
+        match self {
+            Self::Defaults(config) => config.data(),
+            Self::Arguments(arguments) => arguments.data(),
+            Self::Section(section) => section.data(),
+            Self::Definition(definition) => definition.data(),
+            Self::Overrides(overrides) => overrides.data(),
+        }
+    }
+}
+
+impl From<Configuration> for ConfigOrigin {
+    fn from(config: Configuration) -> Self {
+        Self::Defaults(Box::new(config))
+    }
+}
+
+impl From<ConfigDefinition> for ConfigOrigin {
+    fn from(definition: ConfigDefinition) -> Self {
+        Self::Definition(definition)
+    }
+}
+
+impl From<ConfigSection> for ConfigOrigin {
+    fn from(section: ConfigSection) -> Self {
+        Self::Section(section)
+    }
+}
+
+impl From<ConfigOverrides> for ConfigOrigin {
+    fn from(overrides: ConfigOverrides) -> Self {
+        Self::Overrides(overrides)
+    }
+}
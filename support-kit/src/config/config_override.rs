@@ -0,0 +1,142 @@
+use figment::{
+    providers::Serialized,
+    value::{Dict, Map, Value},
+    Figment, Metadata, Profile, Provider,
+};
+use std::str::FromStr;
+
+/// A single config value set on the command line, with `--set key=value` or
+/// `--set-json key=json`. Keys are dotted paths into the configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigOverride {
+    pub key: String,
+    pub value: serde_json::Value,
+}
+
+impl ConfigOverride {
+    /// Parse `key=value`, reading the value the way environment variables
+    /// are read.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let (key, value) = split(input)?;
+        let value = Value::from_str(value).unwrap_or_else(|never| match never {});
+
+        Ok(Self {
+            key,
+            value: serde_json::to_value(value).map_err(|error| error.to_string())?,
+        })
+    }
+
+    /// Parse `key=json`.
+    pub fn parse_json(input: &str) -> Result<Self, String> {
+        let (key, value) = split(input)?;
+        let value = serde_json::from_str(value)
+            .map_err(|error| format!("invalid JSON for `{key}`: {error}"))?;
+
+        Ok(Self { key, value })
+    }
+}
+
+fn split(input: &str) -> Result<(String, &str), String> {
+    match input.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value)),
+        _ => Err(format!("expected `key=value`, got `{input}`")),
+    }
+}
+
+/// Every override from the command line, later ones winning.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigOverrides(pub Vec<ConfigOverride>);
+
+impl ConfigOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Provider for ConfigOverrides {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("command line")
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        self.0
+            .iter()
+            .fold(Figment::new(), |figment, config_override| {
+                figment.merge(Serialized::default(
+                    &config_override.key,
+                    &config_override.value,
+                ))
+            })
+            .data()
+    }
+}
+
+#[test]
+fn overriding_config_from_the_command_line() {
+    use clap::Parser;
+
+    use crate::{Args, SupportControl, Verbosity};
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file(
+            "support-kit.yaml",
+            r#"
+            verbosity: info
+            server:
+              port: 8080
+        "#,
+        )?;
+
+        let args = Args::try_parse_from([
+            "app",
+            "--set",
+            "verbosity=debug",
+            "--set",
+            "server.port=9090",
+            "--set-json",
+            r#"logging=["stdout"]"#,
+        ])
+        .unwrap();
+        let control = SupportControl::load_configuration(&args).unwrap();
+
+        assert_eq!(control.config.verbosity, Verbosity::Debug);
+        assert_eq!(control.config.server.port.as_u16(), Some(9090));
+        assert_eq!(
+            control.config.loggers(),
+            vec![crate::LoggerPreset::Stdout.into()]
+        );
+        assert_eq!(
+            control
+                .explain()
+                .unwrap()
+                .get("server.port")
+                .unwrap()
+                .source,
+            "command line"
+        );
+        assert_eq!(
+            control
+                .explain()
+                .unwrap()
+                .get("verbosity")
+                .unwrap()
+                .overridden,
+            vec!["support-kit.yaml", "defaults"]
+        );
+
+        let args = Args::try_parse_from(["app", "--set", "server.prot=9090"]).unwrap();
+        let error = SupportControl::load_configuration(&args).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("command line: `server.prot` is not a known key, did you mean `port`?"));
+
+        let args = Args::try_parse_from(["app", "--set", "verbosity=loud"]).unwrap();
+
+        assert!(SupportControl::load_configuration(&args).is_err());
+        assert!(Args::try_parse_from(["app", "--set", "verbosity"]).is_err());
+        assert!(Args::try_parse_from(["app", "--set-json", "logging=[stdout"]).is_err());
+
+        Ok(())
+    });
+}
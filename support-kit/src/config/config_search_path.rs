@@ -0,0 +1,139 @@
+use std::path::PathBuf;
+
+use super::ConfigFile;
+
+/// The file name used inside a per-service directory, e.g.
+/// `~/.config/<name>/config.yaml`.
+const SERVICE_DIR_FILE: &str = "config";
+
+/// Where to look for config files. Every candidate is recorded in the
+/// manifest, so [`ConfigManifest::missing`](super::ConfigManifest::missing)
+/// lists everywhere that was searched.
+///
+/// Candidates are merged in this order, later ones winning:
+///
+/// 1. with `system`, `<dir>/<name>/config.*` for each of `$XDG_CONFIG_DIRS`,
+///    then `/etc/<name>/config.*`
+/// 2. `~/<name>.*`
+/// 3. `<config dir>/<name>.*`, then `<config dir>/<name>/config.*`
+/// 4. with `ancestors`, `<ancestor>/<name>.*` for each ancestor of the
+///    working directory, nearest last
+/// 5. `./<name>.*`
+/// 6. `<path>/<name>.*` for each of `paths`
+#[derive(Clone, Debug, Default, PartialEq, bon::Builder)]
+pub struct ConfigSearchPath {
+    /// Extra directories to search, as with `--config-path`.
+    #[builder(default, into)]
+    pub paths: Vec<PathBuf>,
+    /// Search system-wide directories too.
+    #[builder(default)]
+    pub system: bool,
+    /// Search every ancestor of the working directory, the way git looks
+    /// for `.git`.
+    #[builder(default)]
+    pub ancestors: bool,
+}
+
+impl ConfigSearchPath {
+    /// Each directory to search along with the file name to look for in it,
+    /// in merge order.
+    pub fn candidates(&self, file: &ConfigFile) -> Vec<(PathBuf, ConfigFile)> {
+        let service_dir_file = ConfigFile::from(SERVICE_DIR_FILE);
+        let name = file.to_string();
+        let mut candidates = Vec::new();
+
+        if self.system {
+            let mut dirs = xdg_config_dirs();
+            dirs.reverse();
+
+            if cfg!(unix) {
+                dirs.push(PathBuf::from("/etc"));
+            }
+
+            candidates.extend(
+                dirs.into_iter()
+                    .map(|dir| (dir.join(&name), service_dir_file.clone())),
+            );
+        }
+
+        candidates.extend(dirs::home_dir().map(|dir| (dir, file.clone())));
+
+        if let Some(dir) = dirs::config_dir() {
+            candidates.push((dir.clone(), file.clone()));
+            candidates.push((dir.join(&name), service_dir_file));
+        }
+
+        if self.ancestors {
+            let mut ancestors = std::env::current_dir()
+                .map(|dir| {
+                    dir.ancestors()
+                        .skip(1)
+                        .map(PathBuf::from)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            ancestors.reverse();
+
+            candidates.extend(ancestors.into_iter().map(|dir| (dir, file.clone())));
+        }
+
+        candidates.push((PathBuf::new(), file.clone()));
+        candidates.extend(self.paths.iter().map(|dir| (dir.clone(), file.clone())));
+
+        candidates
+    }
+}
+
+/// `$XDG_CONFIG_DIRS`, most important first.
+fn xdg_config_dirs() -> Vec<PathBuf> {
+    match std::env::var("XDG_CONFIG_DIRS") {
+        Ok(dirs) if !dirs.is_empty() => std::env::split_paths(&dirs)
+            .filter(|dir| dir.is_absolute())
+            .collect(),
+        _ if cfg!(unix) => vec![PathBuf::from("/etc/xdg")],
+        _ => Vec::new(),
+    }
+}
+
+#[test]
+fn search_path_candidates() {
+    figment::Jail::expect_with(|jail| {
+        jail.set_env("XDG_CONFIG_DIRS", "/opt/xdg:/usr/xdg");
+
+        let file = ConfigFile::from("app");
+        let candidates = ConfigSearchPath::builder()
+            .system(true)
+            .ancestors(true)
+            .paths(vec![PathBuf::from("deploy")])
+            .build()
+            .candidates(&file);
+        let config = ConfigFile::from(SERVICE_DIR_FILE);
+
+        assert_eq!(
+            &candidates[..3],
+            &[
+                (PathBuf::from("/usr/xdg/app"), config.clone()),
+                (PathBuf::from("/opt/xdg/app"), config.clone()),
+                (PathBuf::from("/etc/app"), config.clone()),
+            ]
+        );
+        assert!(candidates.contains(&(dirs::config_dir().unwrap().join("app"), config)));
+        assert_eq!(
+            &candidates[candidates.len() - 3..],
+            &[
+                (
+                    jail.directory().parent().unwrap().to_path_buf(),
+                    file.clone()
+                ),
+                (PathBuf::new(), file.clone()),
+                (PathBuf::from("deploy"), file.clone()),
+            ]
+        );
+
+        let defaults = ConfigSearchPath::default().candidates(&file);
+
+        assert!(!defaults.iter().any(|(dir, _)| dir.starts_with("/etc")));
+
+        Ok(())
+    });
+}
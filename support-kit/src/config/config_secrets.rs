@@ -0,0 +1,245 @@
+use figment::{
+    value::{Dict, Map, Value},
+    Figment, Metadata, Profile, Provider,
+};
+use regex::{Captures, Regex};
+use secrecy::SecretString;
+use std::{collections::BTreeMap, fmt::Debug, path::PathBuf, sync::OnceLock};
+
+use crate::{SecretReferenceError, ShellCommand, SupportKitError};
+
+/// A pointer to a secret kept outside of config files, written as
+/// `${env:NAME}`, `${file:/path}` or `${cmd:command args}`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SecretReference {
+    Env(String),
+    File(PathBuf),
+    Cmd(String),
+}
+
+impl SecretReference {
+    pub fn new(kind: &str, argument: &str) -> Result<Self, SecretReferenceError> {
+        match kind {
+            "env" => Ok(Self::Env(argument.to_string())),
+            "file" => Ok(Self::File(argument.into())),
+            "cmd" => Ok(Self::Cmd(argument.to_string())),
+            _ => Err(SecretReferenceError::UnknownKind(kind.to_string())),
+        }
+    }
+
+    pub fn resolve(&self) -> Result<String, SecretReferenceError> {
+        match self {
+            Self::Env(name) => {
+                std::env::var(name).map_err(|_| SecretReferenceError::MissingEnvVar(name.clone()))
+            }
+            Self::File(path) => std::fs::read_to_string(path)
+                .map(|contents| contents.trim_end_matches(['\r', '\n']).to_string())
+                .map_err(|error| {
+                    SecretReferenceError::UnreadableFile(path.display().to_string(), error)
+                }),
+            Self::Cmd(command) => Ok(ShellCommand::try_from(command.as_str())?.output()?),
+        }
+    }
+}
+
+fn reference_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+
+    PATTERN.get_or_init(|| Regex::new(r"\$\{(\w+):([^}]*)\}").expect("valid reference pattern"))
+}
+
+/// Values resolved from secret references in the merged sources, keyed by
+/// dotted config key. Debug output only ever lists the keys.
+///
+/// As a provider, this replaces each top level section containing a
+/// reference with a resolved copy, so it's merged after every other source.
+#[derive(Clone, Default)]
+pub struct ConfigSecrets {
+    values: BTreeMap<String, SecretString>,
+    references: BTreeMap<String, String>,
+    resolved: Dict,
+}
+
+impl ConfigSecrets {
+    /// Find every string containing a secret reference and resolve it.
+    pub fn resolve(figment: &Figment) -> Result<Self, SupportKitError> {
+        let merged: Dict = figment.extract()?;
+        let mut secrets = Self::default();
+
+        for (key, value) in merged {
+            let mut value = value;
+
+            if secrets.resolve_value(&key, &mut value)? {
+                secrets.resolved.insert(key, value);
+            }
+        }
+
+        Ok(secrets)
+    }
+
+    fn resolve_value(&mut self, key: &str, value: &mut Value) -> Result<bool, SupportKitError> {
+        let mut changed = false;
+
+        match value {
+            Value::String(_, contents) if reference_pattern().is_match(contents) => {
+                let mut error = None;
+                let resolved = reference_pattern().replace_all(contents, |captures: &Captures| {
+                    SecretReference::new(&captures[1], &captures[2])
+                        .and_then(|reference| reference.resolve())
+                        .unwrap_or_else(|failure| {
+                            error.get_or_insert(failure);
+                            String::new()
+                        })
+                });
+
+                if let Some(error) = error {
+                    return Err(error.into());
+                }
+
+                self.references.insert(key.to_string(), contents.clone());
+                *contents = resolved.into_owned();
+                self.values
+                    .insert(key.to_string(), SecretString::from(contents.clone()));
+                changed = true;
+            }
+            Value::Dict(_, dict) => {
+                for (nested_key, nested_value) in dict.iter_mut() {
+                    changed |= self.resolve_value(&format!("{key}.{nested_key}"), nested_value)?;
+                }
+            }
+            Value::Array(_, items) => {
+                for (index, item) in items.iter_mut().enumerate() {
+                    changed |= self.resolve_value(&format!("{key}.{index}"), item)?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(changed)
+    }
+
+    /// The resolved value for a dotted key, if it came from a reference.
+    pub fn get(&self, key: &str) -> Option<&SecretString> {
+        self.values.get(key)
+    }
+
+    /// The reference a dotted key was written as, like `${env:NAME}`.
+    pub fn reference(&self, key: &str) -> Option<&str> {
+        self.references.get(key).map(String::as_str)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &SecretString> {
+        self.values.values()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl Debug for ConfigSecrets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigSecrets")
+            .field("keys", &self.values.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Provider for ConfigSecrets {
+    fn metadata(&self) -> Metadata {
+        Metadata::named("secret references")
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        Ok(Map::from([(Profile::Default, self.resolved.clone())]))
+    }
+}
+
+#[test]
+fn resolving_secret_references() {
+    use clap::Parser;
+    use secrecy::ExposeSecret;
+
+    use crate::{Args, SupportControl};
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file("registry-token", "ghcr-token\n")?;
+        jail.create_file(
+            "support-kit.yaml",
+            r#"
+            secret: ${env:JWT_SECRET}
+            server:
+              host: ${env:BIND_HOST}
+            deployment:
+              hosts:
+                - address: example.com
+                  auth: ${cmd:echo deploy-key}
+              artifacts:
+                containers:
+                  registry:
+                    account: esmevane
+                    host: ghcr.io
+                    token: ${file:registry-token}
+                  images: []
+        "#,
+        )?;
+
+        jail.set_env("JWT_SECRET", "hunter2");
+        jail.set_env("BIND_HOST", "127.0.0.1");
+
+        let args = Args::try_parse_from("app".split_whitespace()).unwrap();
+        let control = SupportControl::load_configuration(&args).unwrap();
+        let deployment = control.config.deployment.clone().unwrap();
+        let registry = deployment
+            .artifacts
+            .and_then(|artifacts| artifacts.containers)
+            .and_then(|containers| containers.registry)
+            .unwrap();
+
+        assert_eq!(control.config.secret.expose_secret(), "hunter2");
+        assert_eq!(registry.token.expose_secret(), "ghcr-token");
+        assert_eq!(
+            deployment.hosts[0]
+                .auth
+                .as_ref()
+                .map(|auth| auth.expose_secret().to_string()),
+            Some("deploy-key".to_string())
+        );
+        assert_eq!(control.config.server.host.to_string(), "127.0.0.1");
+
+        assert_eq!(
+            control.secrets.keys().collect::<Vec<_>>(),
+            vec![
+                "deployment.artifacts.containers.registry.token",
+                "deployment.hosts.0.auth",
+                "secret",
+                "server.host",
+            ]
+        );
+
+        let debugged = format!("{control:?}");
+
+        assert!(!debugged.contains("hunter2"));
+        assert!(!debugged.contains("ghcr-token"));
+        assert!(!debugged.contains("deploy-key"));
+        assert_eq!(
+            control.secrets.reference("deployment.hosts.0.auth"),
+            Some("${cmd:echo deploy-key}")
+        );
+
+        jail.create_file("support-kit.yaml", "secret: ${env:MISSING_SECRET}")?;
+
+        let error = SupportControl::load_configuration(&args).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "secret reference error: environment variable MISSING_SECRET is not set"
+        );
+
+        Ok(())
+    });
+}
@@ -0,0 +1,155 @@
+use figment::{
+    providers::Serialized,
+    value::{Dict, Map},
+    Metadata, Profile, Provider,
+};
+use schemars::{generate::SchemaGenerator, JsonSchema, Schema};
+use serde::Serialize;
+use std::fmt::Debug;
+
+/// An application's own config section, read from the same files and
+/// environment variables as [`Configuration`](crate::Configuration) under
+/// its own top level key.
+///
+/// Registering a section adds its defaults as the lowest layer, and its
+/// schema to generated schemas and strict validation.
+#[derive(Clone)]
+pub struct ConfigSection {
+    key: String,
+    schema: fn(&mut SchemaGenerator) -> Schema,
+    defaults: fn() -> serde_json::Value,
+}
+
+impl ConfigSection {
+    pub fn new<T>(key: impl Into<String>) -> Self
+    where
+        T: JsonSchema + Serialize + Default,
+    {
+        Self {
+            key: key.into(),
+            schema: |generator| generator.subschema_for::<T>(),
+            defaults: || serde_json::to_value(T::default()).unwrap_or_default(),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn schema(&self, generator: &mut SchemaGenerator) -> Schema {
+        (self.schema)(generator)
+    }
+
+    pub fn defaults(&self) -> serde_json::Value {
+        (self.defaults)()
+    }
+}
+
+impl Debug for ConfigSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigSection")
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+impl PartialEq for ConfigSection {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.defaults() == other.defaults()
+    }
+}
+
+impl Provider for ConfigSection {
+    fn metadata(&self) -> Metadata {
+        Metadata::named(format!("{key} section defaults", key = self.key))
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        Serialized::default(&self.key, self.defaults()).data()
+    }
+}
+
+#[test]
+fn extracting_app_sections() {
+    use clap::Parser;
+    use serde::Deserialize;
+
+    use crate::{Args, SupportControl};
+
+    #[derive(Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+    struct AppConfig {
+        greeting: String,
+        workers: u16,
+    }
+
+    impl Default for AppConfig {
+        fn default() -> Self {
+            Self {
+                greeting: "hello".into(),
+                workers: 1,
+            }
+        }
+    }
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file(
+            "support-kit.yaml",
+            r#"
+            environment: production
+            strict: true
+            app:
+              workers: 4
+        "#,
+        )?;
+        jail.create_file("support-kit.production.yaml", "app:\n  greeting: hi")?;
+        jail.set_env("SUPPORT_KIT__PRODUCTION__APP__WORKERS", "8");
+
+        let args = Args::try_parse_from("app".split_whitespace()).unwrap();
+        let control = SupportControl::load()
+            .args(&args)
+            .sections(vec![ConfigSection::new::<AppConfig>("app")])
+            .call()
+            .unwrap();
+
+        assert_eq!(
+            control.section::<AppConfig>("app").unwrap(),
+            AppConfig {
+                greeting: "hi".into(),
+                workers: 8,
+            }
+        );
+
+        let explanation = control.explain().unwrap();
+
+        assert_eq!(
+            explanation.get("app.workers").unwrap().source,
+            "SUPPORT_KIT__PRODUCTION__APP__WORKERS"
+        );
+        assert_eq!(
+            explanation.get("app.workers").unwrap().overridden,
+            vec!["support-kit.yaml", "app section defaults"]
+        );
+
+        let schema = crate::Configuration::schema_with(&control.sections);
+
+        assert_eq!(
+            schema["properties"]["app"],
+            serde_json::json!({ "$ref": "#/definitions/AppConfig" })
+        );
+        assert!(schema["definitions"]["AppConfig"].is_object());
+
+        jail.create_file("support-kit.yaml", "strict: true\napp:\n  wrokers: 4")?;
+
+        let error = SupportControl::load()
+            .args(&args)
+            .sections(vec![ConfigSection::new::<AppConfig>("app")])
+            .call()
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("`app.wrokers` is not a known key, did you mean `workers`?"));
+
+        Ok(())
+    });
+}
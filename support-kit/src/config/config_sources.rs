@@ -1,64 +1,150 @@
 use bon::builder;
-use figment::{Figment, Provider};
+use figment::Provider;
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
-use std::path::PathBuf;
 
-use crate::{Configuration, Environment};
+use crate::{Environment, EnvironmentConfig};
 
-use super::{ConfigDefinition, ConfigFile, ConfigFormat, ConfigManifest};
+use super::{
+    ConfigDefinition, ConfigFile, ConfigFormat, ConfigManifest, ConfigSearchPath, ConfigVariant,
+};
 
+/// Every source for a config file. For the base config, and then for each
+/// environment, sources are layered in this order, later ones winning:
+///
+/// 1. `name.yaml` (or `name.production.yaml`) along the search path
+/// 2. `name.@host.yaml` (or `name.production.@host.yaml`) for this machine's
+///    hostname, along the search path
+/// 3. `name.local.yaml` (or `name.production.local.yaml`) along the search
+///    path, for a developer's own settings. Keep these out of version
+///    control, e.g. with `*.local.*` in `.gitignore`.
+/// 4. environment variables
 #[derive(Clone, Debug, bon::Builder)]
 #[builder(derive(Clone))]
 pub struct ConfigSources {
     #[builder(default, into)]
     file: ConfigFile,
     env: Option<Environment>,
+    #[builder(default)]
+    search_path: ConfigSearchPath,
+    /// The hostname for host-specific files. Defaults to this machine's.
+    #[builder(into)]
+    hostname: Option<String>,
 }
 
 impl ConfigSources {
     pub fn manifest(&self) -> ConfigManifest {
         let mut definitions = Vec::new();
-        let definition = ConfigDefinition::builder()
-            .maybe_env(self.env)
-            .file(self.file.clone());
+        let definition = ConfigDefinition::builder().maybe_env(self.env.clone());
+        let host = match &self.hostname {
+            Some(hostname) => ConfigVariant::Host(hostname.clone()),
+            None => ConfigVariant::current_host(),
+        };
+
+        for variant in [None, Some(host), Some(ConfigVariant::Local)] {
+            for (path, file) in self.search_path.candidates(&self.file) {
+                let file_definition = definition
+                    .clone()
+                    .maybe_variant(variant.clone())
+                    .path(path)
+                    .file(file);
 
-        for path in canonical_paths() {
-            let file_definition = definition.clone().path(path);
+                for format in ConfigFormat::all() {
+                    let format_definition = file_definition.clone().format(format);
 
-            definitions.push(file_definition.clone().format(ConfigFormat::Yaml).build());
-            definitions.push(file_definition.clone().format(ConfigFormat::Json).build());
-            definitions.push(file_definition.clone().format(ConfigFormat::Toml).build());
+                    definitions.push(format_definition.clone().build());
+                    definitions.push(format_definition.encrypted(true).build());
+                }
+            }
         }
 
-        definitions.push(definition.env_var((self.file.clone(), self.env)).build());
+        definitions.push(
+            definition
+                .file(self.file.clone())
+                .env_var((self.file.clone(), self.env.clone()))
+                .build(),
+        );
 
         ConfigManifest::builder().definitions(definitions).build()
     }
 
+    /// Collect the base sources, then the sources for each environment in
+    /// the active environment's lineage: base -> parent -> environment.
+    /// Parents are declared in the base sources, under `environments`. Files
+    /// that other files extend come just before them. If the base sources
+    /// declare a system service, system-wide paths are searched too.
     pub fn sources(&self) -> figment::Result<ConfigManifest> {
-        let manifest_builder = Self::builder().file(self.file.clone());
-        let mut root_manifest = manifest_builder.clone().build().manifest();
+        let mut search_path = self.search_path.clone();
+        let mut root_manifest = self.root_manifest(&search_path)?;
+        let mut selection: EnvironmentSelection = root_manifest.figment().extract()?;
 
-        let next_env = match self.env {
-            Some(env) => Some(env),
-            None => {
-                let figment = Figment::new().merge(&root_manifest);
+        if selection.service.system && !search_path.system {
+            search_path.system = true;
+            root_manifest = self.root_manifest(&search_path)?;
+            selection = root_manifest.figment().extract()?;
+        }
 
-                figment.extract::<Configuration>()?.environment
-            }
-        };
+        let manifest_builder = Self::builder()
+            .file(self.file.clone())
+            .search_path(search_path)
+            .maybe_hostname(self.hostname.clone());
+        let next_env = self
+            .env
+            .clone()
+            .or(selection.environment)
+            .unwrap_or_default();
 
-        let env_manifest = manifest_builder.env(next_env.unwrap_or_default());
+        let lineage = next_env
+            .lineage(&selection.environments)
+            .map_err(|error| figment::Error::from(error.to_string()))?;
 
-        root_manifest.merge(env_manifest.build().manifest());
+        for env in lineage {
+            root_manifest.merge(
+                manifest_builder
+                    .clone()
+                    .env(env)
+                    .build()
+                    .manifest()
+                    .resolve_extends()?,
+            );
+        }
 
         Ok(root_manifest)
     }
+
+    fn root_manifest(&self, search_path: &ConfigSearchPath) -> figment::Result<ConfigManifest> {
+        Self::builder()
+            .file(self.file.clone())
+            .search_path(search_path.clone())
+            .maybe_hostname(self.hostname.clone())
+            .build()
+            .manifest()
+            .resolve_extends()
+    }
+}
+
+/// The part of the base sources needed to decide which environment sources
+/// to layer on top of them.
+#[derive(Default, Deserialize)]
+struct EnvironmentSelection {
+    #[serde(default)]
+    environment: Option<Environment>,
+    #[serde(default)]
+    environments: BTreeMap<Environment, EnvironmentConfig>,
+    #[serde(default)]
+    service: ServiceSelection,
+}
+
+#[derive(Default, Deserialize)]
+struct ServiceSelection {
+    #[serde(default)]
+    system: bool,
 }
 
 impl Provider for ConfigSources {
     fn metadata(&self) -> figment::Metadata {
-        Default::default()
+        figment::Metadata::named(format!("{file} config sources", file = self.file))
     }
 
     fn data(
@@ -66,23 +152,13 @@ impl Provider for ConfigSources {
     ) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, figment::Error> {
         let sources = self.sources()?;
 
-        Ok(Figment::new().merge(sources).data()?)
+        Ok(sources.figment().data()?)
     }
 }
 
-pub fn canonical_paths() -> Vec<PathBuf> {
-    let mut paths = vec![PathBuf::new()];
-
-    paths.extend(dirs::config_dir());
-    paths.extend(dirs::home_dir());
-    paths.reverse();
-
-    paths
-}
-
 #[test]
 fn basic_manifest_matches() {
-    for format in ConfigFormat::all() {
+    for format in ConfigFormat::BUILT_IN {
         figment::Jail::expect_with(|jail| {
             jail.create_file(format!("support-kit.{format}"), "")?;
             jail.create_file(format!("support-kit.production.{format}"), "")?;
@@ -110,23 +186,26 @@ fn basic_manifest_matches() {
 #[test]
 fn env_specific_manifest_matches() {
     for env in Environment::all() {
-        for format in ConfigFormat::all() {
+        for format in ConfigFormat::BUILT_IN {
             figment::Jail::expect_with(|jail| {
                 jail.create_file(format!("support-kit.{format}"), "")?;
                 jail.create_file(format!("support-kit.{env}.{format}"), "")?;
 
                 let sources = ConfigSources::builder()
                     .file("support-kit")
-                    .env(env)
+                    .env(env.clone())
                     .build();
 
                 assert_eq!(
                     sources.manifest().known(),
                     ConfigManifest::builder()
                         .definitions(bon::vec![
-                            ConfigDefinition::builder().format(format).env(env).build(),
                             ConfigDefinition::builder()
-                                .env_var(("support-kit", env))
+                                .format(format)
+                                .env(env.clone())
+                                .build(),
+                            ConfigDefinition::builder()
+                                .env_var(("support-kit", env.clone()))
                                 .build(),
                         ])
                         .build()
@@ -137,3 +216,322 @@ fn env_specific_manifest_matches() {
         }
     }
 }
+
+#[test]
+fn inherited_environment_manifest_matches() {
+    use super::ConfigFileFormat;
+
+    for format in ConfigFormat::BUILT_IN {
+        figment::Jail::expect_with(|jail| {
+            let staging = Environment::new("staging").unwrap();
+
+            jail.create_file(
+                "support-kit.json",
+                r#"{
+                    "environment": "staging",
+                    "environments": { "staging": { "parent": "production" } }
+                }"#,
+            )?;
+            jail.create_file(
+                format!("support-kit.production.{format}"),
+                format.empty_file_contents(),
+            )?;
+            jail.create_file(
+                format!("support-kit.staging.{format}"),
+                format.empty_file_contents(),
+            )?;
+
+            let sources = ConfigSources::builder().file("support-kit").build();
+
+            assert_eq!(
+                sources.sources()?.known(),
+                ConfigManifest::builder()
+                    .definitions(bon::vec![
+                        ConfigDefinition::builder()
+                            .file("support-kit")
+                            .format(ConfigFormat::Json)
+                            .build(),
+                        ConfigDefinition::builder().env_var("support-kit").build(),
+                        ConfigDefinition::builder()
+                            .format(format)
+                            .env(Environment::PRODUCTION)
+                            .build(),
+                        ConfigDefinition::builder()
+                            .env_var(("support-kit", Environment::PRODUCTION))
+                            .build(),
+                        ConfigDefinition::builder()
+                            .format(format)
+                            .env(staging.clone())
+                            .build(),
+                        ConfigDefinition::builder()
+                            .env_var(("support-kit", staging))
+                            .build(),
+                    ])
+                    .build()
+            );
+
+            Ok(())
+        });
+    }
+}
+
+#[test]
+fn cyclic_environments_are_rejected() {
+    figment::Jail::expect_with(|jail| {
+        jail.create_file(
+            "support-kit.yaml",
+            r#"
+            environment: staging
+            environments:
+                staging:
+                    parent: qa
+                qa:
+                    parent: staging
+        "#,
+        )?;
+
+        let sources = ConfigSources::builder().file("support-kit").build();
+        let error = sources.sources().unwrap_err();
+
+        assert!(error.to_string().contains("staging -> qa -> staging"));
+
+        Ok(())
+    });
+}
+
+#[test]
+fn extended_manifest_matches() {
+    use crate::{Configuration, Verbosity};
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_dir("shared")?;
+        jail.create_file(
+            "shared/common.json",
+            r#"{ "verbosity": "debug", "color": "never" }"#,
+        )?;
+        jail.create_file(
+            "shared/base.toml",
+            r#"
+            extends = "common.json"
+            verbosity = "info"
+        "#,
+        )?;
+        jail.create_file(
+            "support-kit.yaml",
+            r#"
+            extends:
+              - shared/base.toml
+        "#,
+        )?;
+
+        let sources = ConfigSources::builder().file("support-kit").build();
+        let manifest = sources.sources()?.known();
+
+        assert_eq!(
+            manifest,
+            ConfigManifest::builder()
+                .definitions(bon::vec![
+                    ConfigDefinition::Json("shared/common.json".into()),
+                    ConfigDefinition::Toml("shared/base.toml".into()),
+                    ConfigDefinition::builder()
+                        .file("support-kit")
+                        .format(ConfigFormat::Yaml)
+                        .build(),
+                    ConfigDefinition::builder().env_var("support-kit").build(),
+                    ConfigDefinition::builder()
+                        .env_var(("support-kit", Environment::DEVELOPMENT))
+                        .build(),
+                ])
+                .build()
+        );
+
+        let config: Configuration = manifest.figment().extract()?;
+
+        assert_eq!(config.verbosity, Verbosity::Info);
+        assert_eq!(config.color, crate::Color::Never);
+
+        Ok(())
+    });
+}
+
+#[test]
+fn cyclic_extends_are_rejected() {
+    figment::Jail::expect_with(|jail| {
+        jail.create_file("support-kit.yaml", "extends: other.json")?;
+        jail.create_file("other.json", r#"{ "include": ["support-kit.yaml"] }"#)?;
+
+        let sources = ConfigSources::builder().file("support-kit").build();
+        let error = sources.sources().unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("support-kit.yaml -> other.json -> support-kit.yaml"));
+
+        jail.create_file("other.json", r#"{ "extends": "missing.toml" }"#)?;
+
+        let error = sources.sources().unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("missing config file missing.toml, extended by other.json"));
+
+        Ok(())
+    });
+}
+
+#[test]
+fn registered_formats_are_sources() {
+    use figment::value::{Dict, Value};
+
+    use crate::{Configuration, Verbosity};
+
+    use super::ConfigFileFormat;
+
+    /// `key value` lines.
+    #[derive(Debug)]
+    struct Spaced;
+
+    impl ConfigFileFormat for Spaced {
+        fn extension(&self) -> &str {
+            "spaced"
+        }
+
+        fn parse(&self, contents: &str) -> figment::Result<Dict> {
+            Ok(contents
+                .lines()
+                .filter_map(|line| line.split_once(' '))
+                .map(|(key, value)| (key.to_string(), Value::from(value)))
+                .collect())
+        }
+    }
+
+    /// Unregisters the format when dropped, even if an assert panics.
+    struct Registered;
+
+    impl Drop for Registered {
+        fn drop(&mut self) {
+            ConfigFormat::unregister("spaced");
+        }
+    }
+
+    // Inside the jail, so other tests reading config files never see it.
+    figment::Jail::expect_with(|jail| {
+        ConfigFormat::register(Spaced);
+        let _registered = Registered;
+
+        jail.create_file("support-kit.spaced", "verbosity warn\ncolor never")?;
+        jail.create_file("support-kit.production.env", "VERBOSITY=trace")?;
+
+        let sources = ConfigSources::builder()
+            .file("support-kit")
+            .env(Environment::PRODUCTION)
+            .build();
+        let manifest = sources.sources()?.known();
+        let spaced = ConfigFormat::from_path("support-kit.spaced".as_ref()).unwrap();
+
+        assert_eq!(
+            manifest.definitions()[0],
+            ConfigDefinition::builder()
+                .file("support-kit")
+                .format(spaced)
+                .build()
+        );
+        assert_eq!(
+            manifest.definitions()[2],
+            ConfigDefinition::Dotenv("support-kit.production.env".into())
+        );
+
+        let config: Configuration = manifest.figment().extract()?;
+
+        assert_eq!(config.verbosity, Verbosity::Trace);
+        assert_eq!(config.color, crate::Color::Never);
+
+        Ok(())
+    });
+
+    assert_eq!(ConfigFormat::from_path("support-kit.spaced".as_ref()), None);
+}
+
+#[test]
+fn searching_configured_paths() {
+    use clap::Parser;
+
+    use crate::{Args, Color, SupportControl, Verbosity};
+
+    figment::Jail::expect_with(|jail| {
+        let xdg = jail.directory().join("xdg");
+
+        jail.create_dir("xdg/support-kit")?;
+        jail.create_dir("deploy")?;
+        jail.create_file("support-kit.yaml", "service:\n  system: true")?;
+        jail.create_file(
+            "xdg/support-kit/config.yaml",
+            "color: never\nverbosity: info",
+        )?;
+        jail.create_file("deploy/support-kit.yaml", "verbosity: debug")?;
+        jail.set_env("XDG_CONFIG_DIRS", xdg.display());
+
+        let args = Args::try_parse_from("app --config-path deploy".split_whitespace()).unwrap();
+        let control = SupportControl::load_configuration(&args).unwrap();
+
+        assert_eq!(control.config.color, Color::Never);
+        assert_eq!(control.config.verbosity, Verbosity::Debug);
+        let missing = control.manifest().unwrap().missing();
+
+        for searched in [
+            "/etc/support-kit/config.yaml",
+            "deploy/support-kit.development.toml",
+        ] {
+            assert!(missing
+                .definitions()
+                .contains(&ConfigDefinition::NotFound(searched.into())));
+        }
+
+        Ok(())
+    });
+}
+
+#[test]
+fn local_and_host_manifest_matches() {
+    use crate::{Color, Configuration, Verbosity};
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file("support-kit.yaml", "verbosity: error\ncolor: never")?;
+        jail.create_file("support-kit.@devbox.yaml", "verbosity: warn")?;
+        jail.create_file("support-kit.local.yaml", "verbosity: info")?;
+        jail.create_file("support-kit.@otherbox.yaml", "color: always")?;
+        jail.create_file("support-kit.production.yaml", "verbosity: debug")?;
+        jail.create_file("support-kit.production.local.yaml", "verbosity: trace")?;
+
+        let sources = ConfigSources::builder()
+            .file("support-kit")
+            .env(Environment::PRODUCTION)
+            .hostname("devbox")
+            .build();
+        let manifest = sources.sources()?.known();
+
+        assert_eq!(
+            manifest,
+            ConfigManifest::builder()
+                .definitions(bon::vec![
+                    ConfigDefinition::Yaml("support-kit.yaml".into()),
+                    ConfigDefinition::Yaml("support-kit.@devbox.yaml".into()),
+                    ConfigDefinition::Yaml("support-kit.local.yaml".into()),
+                    ConfigDefinition::builder().env_var("support-kit").build(),
+                    ConfigDefinition::Yaml("support-kit.production.yaml".into()),
+                    ConfigDefinition::Yaml("support-kit.production.local.yaml".into()),
+                    ConfigDefinition::builder()
+                        .env_var(("support-kit", Environment::PRODUCTION))
+                        .build(),
+                ])
+                .build()
+        );
+
+        let config: Configuration = manifest.figment().extract()?;
+
+        assert_eq!(config.verbosity, Verbosity::Trace);
+        assert_eq!(config.color, Color::Never);
+
+        Ok(())
+    });
+}
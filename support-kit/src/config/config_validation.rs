@@ -0,0 +1,578 @@
+use figment::Provider;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::{fmt::Display, path::Path};
+
+use crate::{
+    ConfigValidationError, Configuration, Environment, HostDetails, LoggerConfig, OneOrMany,
+    SecurityConfig,
+};
+
+use super::{ConfigDefinition, ConfigOrigin};
+
+/// Keys allowed at the top of any config file, beyond those in the schema.
+const ROOT_KEYS: &[&str] = &["$schema", "extends", "include"];
+
+/// A problem found in the configuration, along with where it was read from
+/// when that's known.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigIssue {
+    pub key: String,
+    pub message: String,
+    pub source: Option<String>,
+    pub line: Option<usize>,
+}
+
+impl Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.source, self.line) {
+            (Some(source), Some(line)) => write!(f, "{source}:{line}: ")?,
+            (Some(source), None) => write!(f, "{source}: ")?,
+            _ => {}
+        }
+
+        write!(
+            f,
+            "`{key}` {message}",
+            key = self.key,
+            message = self.message
+        )
+    }
+}
+
+/// Every issue found by strict validation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigValidation {
+    issues: Vec<ConfigIssue>,
+}
+
+impl ConfigValidation {
+    /// Check every source for keys the configuration doesn't know about, then
+    /// check the merged configuration for values that can't work.
+    pub fn check(origins: &[ConfigOrigin], config: &Configuration) -> crate::Result<Self> {
+        let schema = schema(origins);
+        let mut validation = Self::default();
+
+        for origin in origins {
+            validation.issues.extend(key_issues(&schema, origin)?);
+        }
+
+        for (key, message) in semantic_issues(config) {
+            validation.issues.push(locate(origins, key, message));
+        }
+
+        Ok(validation)
+    }
+
+    /// Check only `--set` and `--set-json` for keys the configuration
+    /// doesn't know about. Overrides are checked even when validation isn't
+    /// strict, since a key that isn't known would silently do nothing.
+    pub fn check_overrides(origins: &[ConfigOrigin]) -> crate::Result<Self> {
+        let schema = schema(origins);
+        let mut validation = Self::default();
+
+        for origin in origins {
+            if let ConfigOrigin::Overrides(_) = origin {
+                validation.issues.extend(key_issues(&schema, origin)?);
+            }
+        }
+
+        Ok(validation)
+    }
+
+    pub fn issues(&self) -> &[ConfigIssue] {
+        &self.issues
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<(), ConfigValidationError> {
+        if self.is_valid() {
+            Ok(())
+        } else {
+            Err(ConfigValidationError(self.issues))
+        }
+    }
+}
+
+/// Resolve `$ref`s and flatten `allOf`/`anyOf`/`oneOf` into the concrete
+/// schemas a value could match.
+/// The schema of the configuration, with any registered sections.
+fn schema(origins: &[ConfigOrigin]) -> Value {
+    let sections = origins
+        .iter()
+        .filter_map(|origin| match origin {
+            ConfigOrigin::Section(section) => Some(section.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    Configuration::schema_with(&sections)
+}
+
+/// Keys set by a file, environment variable or override that the schema
+/// doesn't know about.
+fn key_issues(schema: &Value, origin: &ConfigOrigin) -> crate::Result<Vec<ConfigIssue>> {
+    let definition = match origin {
+        ConfigOrigin::Definition(definition) => Some(definition),
+        ConfigOrigin::Overrides(_) => None,
+        _ => return Ok(Vec::new()),
+    };
+    let mut issues = Vec::new();
+
+    for dict in origin.data()?.values() {
+        let mut unknown = Vec::new();
+        let value = serde_json::to_value(dict).unwrap_or_default();
+
+        unknown_keys(schema, schema, &value, "", &mut unknown);
+
+        for (key, known) in unknown {
+            // The base environment variable prefix also picks up
+            // variables meant for specific environments.
+            let environment_scoped = matches!(definition, Some(ConfigDefinition::EnvVar(_)))
+                && !key.contains('.')
+                && Environment::new(key.replace('_', "-")).is_ok();
+
+            if environment_scoped || ROOT_KEYS.contains(&key.as_str()) {
+                continue;
+            }
+
+            let name = key.rsplit('.').next().unwrap_or(&key);
+            let message = match suggest(name, &known) {
+                Some(suggestion) => format!("is not a known key, did you mean `{suggestion}`?"),
+                None => "is not a known key".to_string(),
+            };
+
+            issues.push(ConfigIssue {
+                source: Some(origin.describe(&key)),
+                line: definition
+                    .and_then(ConfigDefinition::path)
+                    .and_then(|path| find_line(path, &key)),
+                key,
+                message,
+            });
+        }
+    }
+
+    Ok(issues)
+}
+
+fn alternatives<'a>(root: &'a Value, schema: &'a Value) -> Vec<&'a Value> {
+    if let Some(name) = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix("#/definitions/"))
+    {
+        return root["definitions"]
+            .get(name)
+            .map(|definition| alternatives(root, definition))
+            .unwrap_or_default();
+    }
+
+    let nested: Vec<&Value> = ["allOf", "anyOf", "oneOf"]
+        .iter()
+        .filter_map(|combinator| schema.get(combinator)?.as_array())
+        .flatten()
+        .flat_map(|alternative| alternatives(root, alternative))
+        .collect();
+
+    if nested.is_empty() {
+        vec![schema]
+    } else {
+        nested
+    }
+}
+
+/// Collect every key in `value` the schema doesn't describe, along with the
+/// keys that would have been accepted in its place.
+fn unknown_keys(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    path: &str,
+    found: &mut Vec<(String, Vec<String>)>,
+) {
+    let schemas = alternatives(root, schema);
+    let join = |key: &str| match path {
+        "" => key.to_string(),
+        _ => format!("{path}.{key}"),
+    };
+
+    match value {
+        Value::Object(map) => {
+            let objects: Vec<&Value> = schemas
+                .into_iter()
+                .filter(|schema| {
+                    schema.get("properties").is_some() || schema.get("patternProperties").is_some()
+                })
+                .collect();
+
+            if objects.is_empty() {
+                return;
+            }
+
+            let known: Vec<String> = objects
+                .iter()
+                .filter_map(|schema| schema.get("properties")?.as_object())
+                .flat_map(|properties| properties.keys().cloned())
+                .collect();
+
+            let open: Vec<Value> = objects
+                .iter()
+                .filter_map(|schema| schema.get("patternProperties")?.as_object())
+                .flat_map(|patterns| patterns.values().cloned())
+                .collect();
+
+            for (key, child) in map {
+                let matching: Vec<Value> = objects
+                    .iter()
+                    .filter_map(|schema| schema.get("properties")?.get(key).cloned())
+                    .collect();
+
+                if !matching.is_empty() {
+                    unknown_keys(
+                        root,
+                        &json!({ "anyOf": matching }),
+                        child,
+                        &join(key),
+                        found,
+                    );
+                } else if !open.is_empty() {
+                    unknown_keys(root, &json!({ "anyOf": open }), child, &join(key), found);
+                } else {
+                    found.push((join(key), known.clone()));
+                }
+            }
+        }
+        Value::Array(items) => {
+            let item_schemas: Vec<Value> = schemas
+                .into_iter()
+                .filter_map(|schema| schema.get("items").cloned())
+                .collect();
+
+            if item_schemas.is_empty() {
+                return;
+            }
+
+            let schema = json!({ "anyOf": item_schemas });
+
+            for (index, item) in items.iter().enumerate() {
+                unknown_keys(root, &schema, item, &join(&index.to_string()), found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The known key closest to the given one, if any is close enough.
+fn suggest<'a>(key: &str, known: &'a [String]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|candidate| (strsim::jaro_winkler(key, candidate), candidate))
+        .filter(|(score, _)| *score > 0.8)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// Values that parse fine but can't work at runtime.
+fn semantic_issues(config: &Configuration) -> Vec<(String, String)> {
+    let mut issues = Vec::new();
+
+    if config.server.port.as_u16().is_none() {
+        issues.push((
+            "server.port".to_string(),
+            format!(
+                "must be between 0 and 65535, got {port}",
+                port = config.server.port
+            ),
+        ));
+    }
+
+    if let Err(error) = config.redaction.regexes() {
+        issues.push((
+            "redaction.patterns".to_string(),
+            format!("is not a valid pattern: {error}"),
+        ));
+    }
+
+    if !config.metrics.path.starts_with('/') {
+        issues.push((
+            "metrics.path".to_string(),
+            format!(
+                "must start with /, got {path:?}",
+                path = config.metrics.path
+            ),
+        ));
+    }
+
+    let loggers = match &config.logging {
+        OneOrMany::One(logger) => vec![("logging".to_string(), logger.clone())],
+        OneOrMany::Many(loggers) => loggers
+            .iter()
+            .enumerate()
+            .map(|(index, logger)| (format!("logging.{index}"), logger.clone()))
+            .collect(),
+    };
+
+    for (key, logger) in loggers {
+        let logger = LoggerConfig::from(logger);
+
+        if let Err(error) = logger.directives() {
+            issues.push((
+                format!("{key}.filter"),
+                format!("is not a valid filter: {error}"),
+            ));
+        }
+
+        if let Some(sampling) = logger.otlp().and_then(|otlp| otlp.sampling) {
+            if !(0.0..=1.0).contains(&sampling) {
+                issues.push((
+                    format!("{key}.otlp.sampling"),
+                    format!("must be between 0 and 1, got {sampling}"),
+                ));
+            }
+        }
+    }
+
+    let Some(deployment) = &config.deployment else {
+        return issues;
+    };
+
+    match &deployment.security {
+        SecurityConfig::Acme { domains, .. } if domains.is_empty() => issues.push((
+            "deployment.security.domains".to_string(),
+            "must list at least one domain for ACME certificates".to_string(),
+        )),
+        SecurityConfig::Unknown(_) => issues.push((
+            "deployment.security".to_string(),
+            "is not a recognized security config, expected `type: acme`".to_string(),
+        )),
+        _ => {}
+    }
+
+    for (index, host) in deployment.hosts.iter().enumerate() {
+        let auth = HostDetails::from(host.clone()).auth;
+        let readable =
+            crate::hosts::expand_tilde(&auth).is_some_and(|path| std::fs::File::open(path).is_ok());
+
+        if !readable {
+            issues.push((
+                format!("deployment.hosts.{index}.auth"),
+                format!("points to a key that can't be read: {auth}"),
+            ));
+        }
+    }
+
+    let containers = deployment
+        .artifacts
+        .as_ref()
+        .and_then(|artifacts| artifacts.containers.as_ref());
+
+    if let Some(containers) = containers {
+        if !containers.images.is_empty() && containers.registry.is_none() {
+            issues.push((
+                "deployment.artifacts.containers.registry".to_string(),
+                "is required when container images are defined".to_string(),
+            ));
+        }
+    }
+
+    issues
+}
+
+/// Attribute a key to the last source that set it, or the closest parent key
+/// that was set, so defaults still point somewhere useful.
+fn locate(origins: &[ConfigOrigin], key: String, message: String) -> ConfigIssue {
+    let mut segments: Vec<&str> = key.split('.').collect();
+
+    while !segments.is_empty() {
+        let pointer = format!("/{}", segments.join("/"));
+
+        for origin in origins.iter().rev() {
+            let definition = match origin {
+                ConfigOrigin::Definition(definition) => Some(definition),
+                ConfigOrigin::Overrides(_) => None,
+                _ => continue,
+            };
+
+            let provided = origin.data().is_ok_and(|data| {
+                data.values().any(|dict| {
+                    serde_json::to_value(dict).is_ok_and(|value| value.pointer(&pointer).is_some())
+                })
+            });
+
+            if provided {
+                let found = segments.join(".");
+
+                return ConfigIssue {
+                    source: Some(origin.describe(&found)),
+                    line: definition
+                        .and_then(ConfigDefinition::path)
+                        .and_then(|path| find_line(path, &found)),
+                    key,
+                    message,
+                };
+            }
+        }
+
+        segments.pop();
+    }
+
+    ConfigIssue {
+        key,
+        message,
+        source: None,
+        line: None,
+    }
+}
+
+/// Find the line a dotted key is declared on by looking for each segment in
+/// turn, starting from where its parent was found. Works for the nesting
+/// styles of YAML, TOML and pretty-printed JSON; list indexes are skipped.
+fn find_line(path: &Path, key: &str) -> Option<usize> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut start = 0;
+
+    for segment in key.split('.') {
+        if segment.parse::<usize>().is_ok() {
+            continue;
+        }
+
+        let pattern = Regex::new(&format!(
+            r#"(^|[\s{{,.\[-])["']?{segment}["']?\s*[:=\].]"#,
+            segment = regex::escape(segment)
+        ))
+        .ok()?;
+
+        start += lines[start..]
+            .iter()
+            .position(|line| pattern.is_match(line))?;
+    }
+
+    Some(start + 1)
+}
+
+#[test]
+fn strict_validation() {
+    use clap::Parser;
+
+    use crate::{Args, SupportControl, SupportKitError};
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file(
+            "support-kit.yaml",
+            r#"
+server:
+  port: 70000
+service:
+  name: support-kit
+  service_manager: systemd
+verbosty: debug
+deployment:
+  hosts:
+    - address: example.com
+      auth: ./missing-key
+  security:
+    type: acme
+    domains: []
+    emails: []
+    production: false
+"#,
+        )?;
+
+        jail.set_env("SUPPORT_KIT__PRODUCTION__VERBOSITY", "trace");
+
+        let args = Args::try_parse_from("app".split_whitespace()).unwrap();
+        assert!(SupportControl::load_configuration(&args).is_ok());
+
+        let args = Args::try_parse_from("app --strict".split_whitespace()).unwrap();
+        let Err(SupportKitError::ConfigValidationError(error)) =
+            SupportControl::load_configuration(&args)
+        else {
+            panic!("expected strict validation to fail");
+        };
+
+        let issue = |key: &str| {
+            error
+                .0
+                .iter()
+                .find(|issue| issue.key == key)
+                .cloned()
+                .unwrap_or_else(|| panic!("no issue for {key}: {error}"))
+        };
+
+        assert_eq!(
+            issue("service.service_manager"),
+            ConfigIssue {
+                key: "service.service_manager".into(),
+                message: "is not a known key, did you mean `service-manager`?".into(),
+                source: Some("support-kit.yaml".into()),
+                line: Some(6),
+            }
+        );
+
+        assert_eq!(issue("verbosty").line, Some(7));
+        assert_eq!(issue("server.port").line, Some(3));
+        assert_eq!(issue("deployment.hosts.0.auth").line, Some(11));
+        assert_eq!(issue("deployment.security.domains").line, Some(14));
+        assert_eq!(error.0.len(), 5, "{error}");
+
+        assert!(error.to_string().contains(
+            "support-kit.yaml:7: `verbosty` is not a known key, did you mean `verbosity`?"
+        ));
+
+        Ok(())
+    });
+}
+
+#[test]
+fn strict_mode_from_config() {
+    use clap::Parser;
+
+    use crate::{Args, SupportControl};
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file(
+            "support-kit.json",
+            r#"{
+    "$schema": "./support-kit.schema.json",
+    "strict": true,
+    "deployment": {
+        "hosts": [],
+        "artifacts": {
+            "containers": {
+                "images": [
+                    {
+                        "definition": "Dockerfile",
+                        "name": "app",
+                        "label": "latest",
+                        "namespace": "esmevane",
+                        "repo": "app"
+                    }
+                ]
+            }
+        },
+        "security": { "type": "self-signed" }
+    }
+}"#,
+        )?;
+
+        let args = Args::try_parse_from("app".split_whitespace()).unwrap();
+        let error = SupportControl::load_configuration(&args)
+            .expect_err("strict mode should be read from config")
+            .to_string();
+
+        assert!(error.contains(
+            "support-kit.json:7: `deployment.artifacts.containers.registry` is required"
+        ));
+        assert!(error.contains(
+            "support-kit.json:19: `deployment.security` is not a recognized security config"
+        ));
+        assert!(!error.contains("$schema"));
+
+        Ok(())
+    });
+}
@@ -0,0 +1,27 @@
+use std::fmt::Display;
+
+/// A file layered over a base or environment config file, named with an
+/// extra part before the extension: `name.@host.yaml` for one machine, and
+/// `name.local.yaml` for a developer's own settings, which shouldn't be
+/// committed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigVariant {
+    Host(String),
+    Local,
+}
+
+impl ConfigVariant {
+    /// The host variant for the machine this is running on.
+    pub fn current_host() -> Self {
+        Self::Host(gethostname::gethostname().to_string_lossy().to_lowercase())
+    }
+}
+
+impl Display for ConfigVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Host(host) => write!(f, "@{host}"),
+            Self::Local => write!(f, "local"),
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::{path::PathBuf, time::Duration};
+use tokio::{
+    sync::{broadcast, watch},
+    task::JoinHandle,
+};
+
+use crate::{Args, Configuration, Logging, SupportControl};
+
+use super::{ConfigDefinition, ConfigDiff, ConfigSearchPath, ConfigSection};
+
+/// Reloads configuration when its files change, or on SIGHUP when enabled.
+/// A reload that fails is logged and the last good configuration is kept.
+///
+/// Dropping the watcher stops it.
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    config: watch::Receiver<Configuration>,
+    changes: broadcast::Sender<ConfigDiff>,
+    task: JoinHandle<()>,
+}
+
+#[bon::bon]
+impl ConfigWatcher {
+    /// Start watching from the given control's arguments and configuration.
+    /// This spawns a task, so it has to be called from a tokio runtime.
+    #[builder]
+    pub fn new(
+        control: &SupportControl,
+        #[builder(default = Duration::from_secs(1))] interval: Duration,
+        #[builder(default)] sighup: bool,
+    ) -> Self {
+        let (sender, config) = watch::channel(control.config.clone());
+        let (changes, _) = broadcast::channel(16);
+        let task = tokio::spawn(watch_sources(
+            control.args.clone(),
+            control.sections.clone(),
+            control.search_path.clone(),
+            sender,
+            changes.clone(),
+            interval,
+            sighup,
+        ));
+
+        Self {
+            config,
+            changes,
+            task,
+        }
+    }
+}
+
+impl ConfigWatcher {
+    /// The latest good configuration.
+    pub fn receiver(&self) -> watch::Receiver<Configuration> {
+        self.config.clone()
+    }
+
+    /// What changed in each successful reload.
+    pub fn changes(&self) -> broadcast::Receiver<ConfigDiff> {
+        self.changes.subscribe()
+    }
+
+    pub fn current(&self) -> Configuration {
+        self.config.borrow().clone()
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The contents of every file the sources could be read from, so a change,
+/// a new file or a removed file all trigger a reload.
+#[derive(Debug, Default, PartialEq)]
+struct Snapshot(Vec<(PathBuf, Option<Vec<u8>>)>);
+
+impl Snapshot {
+    fn take(control: &SupportControl) -> Self {
+        let paths = control
+            .manifest()
+            .map(|manifest| {
+                manifest
+                    .definitions()
+                    .iter()
+                    .filter_map(|definition| match definition {
+                        ConfigDefinition::NotFound(path) => Some(path.clone()),
+                        definition => definition.path().map(PathBuf::from),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        Self(
+            paths
+                .into_iter()
+                .map(|path| {
+                    let contents = std::fs::read(&path).ok();
+                    (path, contents)
+                })
+                .collect(),
+        )
+    }
+}
+
+async fn watch_sources(
+    args: Args,
+    sections: Vec<ConfigSection>,
+    search_path: ConfigSearchPath,
+    sender: watch::Sender<Configuration>,
+    changes: broadcast::Sender<ConfigDiff>,
+    interval: Duration,
+    sighup: bool,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    let mut hangup = Hangup::new(sighup);
+    let mut watched = SupportControl::builder()
+        .args(args.clone())
+        .config(sender.borrow().clone())
+        .sections(sections.clone())
+        .search_path(search_path.clone())
+        .build();
+    let mut snapshot = Snapshot::take(&watched);
+
+    ticker.tick().await;
+
+    loop {
+        let forced = tokio::select! {
+            _ = ticker.tick() => false,
+            _ = hangup.recv() => true,
+        };
+
+        let next = Snapshot::take(&watched);
+
+        if !forced && next == snapshot {
+            continue;
+        }
+
+        snapshot = next;
+
+        let control = match SupportControl::load()
+            .args(&args)
+            .sections(sections.clone())
+            .search_path(search_path.clone())
+            .call()
+        {
+            Ok(control) => control,
+            Err(error) => {
+                tracing::error!(%error, "unable to reload configuration, keeping the last good one");
+                continue;
+            }
+        };
+
+        // The files to watch can change along with the config, e.g. when the
+        // environment or an `extends` list changes.
+        snapshot = Snapshot::take(&control);
+
+        let diff = match ConfigDiff::between(&*sender.borrow(), &control.config) {
+            Ok(diff) => diff,
+            Err(error) => {
+                tracing::error!(%error, "unable to compare configurations");
+                continue;
+            }
+        };
+
+        watched = control;
+
+        if diff.is_empty() {
+            continue;
+        }
+
+        tracing::info!(changes = %diff, "configuration reloaded");
+
+        if let Some(logging) = Logging::handle() {
+            logging.apply(&watched.config);
+        }
+
+        sender.send_replace(watched.config.clone());
+        let _ = changes.send(diff);
+    }
+}
+
+/// SIGHUP, when asked for and supported.
+struct Hangup {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl Hangup {
+    fn new(enabled: bool) -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let signal = enabled
+                .then(|| signal(SignalKind::hangup()))
+                .and_then(|signal| {
+                    signal
+                        .map_err(|error| tracing::error!(%error, "unable to listen for SIGHUP"))
+                        .ok()
+                });
+
+            Self { signal }
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = enabled;
+            Self {}
+        }
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = &mut self.signal {
+            signal.recv().await;
+            return;
+        }
+
+        std::future::pending::<()>().await
+    }
+}
+
+#[test]
+fn reloading_changed_config() {
+    use crate::Verbosity;
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file("support-kit.yaml", "verbosity: info")?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let args = Args::default();
+            let control = SupportControl::load_configuration(&args).unwrap();
+            let watcher = ConfigWatcher::builder()
+                .control(&control)
+                .interval(Duration::from_millis(10))
+                .build();
+            let mut changes = watcher.changes();
+            let receiver = watcher.receiver();
+
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            jail.create_file("support-kit.yaml", "verbosity: debug")
+                .unwrap();
+
+            let diff = tokio::time::timeout(Duration::from_secs(5), changes.recv())
+                .await
+                .expect("config should reload")
+                .unwrap();
+
+            assert_eq!(
+                diff.get("verbosity")
+                    .and_then(|change| change.after.clone()),
+                Some("debug".into())
+            );
+            assert_eq!(receiver.borrow().verbosity, Verbosity::Debug);
+
+            jail.create_file("support-kit.yaml", "verbosity: [")
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            assert_eq!(watcher.current().verbosity, Verbosity::Debug);
+        });
+
+        Ok(())
+    });
+}
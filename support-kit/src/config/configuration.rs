@@ -1,13 +1,16 @@
 use figment::{providers::Serialized, Figment, Provider};
+use schemars::{generate::SchemaSettings, JsonSchema};
 use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 use crate::{
-    Args, Color, DeploymentConfig, DeploymentControl, Environment, LoggerConfig, Logging,
-    LoggingConfig, NetworkConfig, ServiceConfig, ServiceName, Verbosity,
+    Args, Color, ConfigSection, DeploymentConfig, DeploymentControl, Environment,
+    EnvironmentConfig, LogRedactionConfig, LoggerConfig, Logging, LoggingConfig, MetricsConfig,
+    NetworkConfig, ServiceConfig, ServiceName, Verbosity,
 };
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize, bon::Builder)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, bon::Builder)]
 pub struct Configuration {
     #[serde(default)]
     #[builder(default, into)]
@@ -17,6 +20,11 @@ pub struct Configuration {
     #[builder(default, into)]
     pub verbosity: Verbosity,
 
+    /// What to mask in log output, on top of known secrets.
+    #[serde(default)]
+    #[builder(default)]
+    pub redaction: LogRedactionConfig,
+
     #[serde(default)]
     #[builder(default, into)]
     pub color: Color,
@@ -29,16 +37,30 @@ pub struct Configuration {
     #[builder(default, into)]
     pub service: ServiceConfig,
 
+    #[serde(default)]
+    #[builder(default)]
+    pub metrics: MetricsConfig,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     #[builder(into)]
     pub environment: Option<Environment>,
 
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[builder(default, into)]
+    pub environments: BTreeMap<Environment, EnvironmentConfig>,
+
     #[serde(default)]
     #[builder(into)]
     pub deployment: Option<DeploymentConfig>,
 
+    /// Reject unknown keys and values that can't work when loading.
+    #[serde(default)]
+    #[builder(default)]
+    pub strict: bool,
+
     #[serde(default, skip_serializing)]
     #[builder(default)]
+    #[schemars(with = "String")]
     pub secret: SecretString,
 }
 
@@ -47,10 +69,14 @@ impl Configuration {
         self.color.init();
     }
 
-    pub fn init_logging(&self) -> Vec<tracing_appender::non_blocking::WorkerGuard> {
+    pub fn init_logging(&self) -> Vec<crate::LoggingGuard> {
         Logging::initialize(self.clone())
     }
 
+    pub fn try_init_logging(&self) -> Result<Vec<crate::LoggingGuard>, crate::LoggingError> {
+        Logging::try_initialize(self.clone())
+    }
+
     pub async fn init_tls(&self) -> Option<rustls_acme::axum::AxumAcceptor> {
         match &self.deployment {
             Some(deployment) => DeploymentControl::initialize(deployment).await,
@@ -62,6 +88,44 @@ impl Configuration {
         self.logging.loggers()
     }
 
+    /// The active environment and its parents, most distant ancestor first.
+    /// Falls back to just the active environment if the parents form a loop.
+    pub fn environment_lineage(&self) -> Vec<Environment> {
+        let environment = self.environment.clone().unwrap_or_default();
+
+        environment
+            .lineage(&self.environments)
+            .unwrap_or_else(|_| vec![environment])
+    }
+
+    /// A JSON Schema (draft 7) describing every config file this crate reads,
+    /// for editor completion and validation.
+    pub fn schema() -> serde_json::Value {
+        Self::schema_with(&[])
+    }
+
+    /// The schema with each application section added as a top level key.
+    pub fn schema_with(sections: &[ConfigSection]) -> serde_json::Value {
+        let mut generator = SchemaSettings::draft07().into_generator();
+        let properties = sections
+            .iter()
+            .map(|section| {
+                let schema = section.schema(&mut generator);
+                (section.key().to_string(), schema.to_value())
+            })
+            .collect::<Vec<_>>();
+        let mut schema = generator.into_root_schema_for::<Self>().to_value();
+
+        if let Some(root) = schema
+            .get_mut("properties")
+            .and_then(serde_json::Value::as_object_mut)
+        {
+            root.extend(properties);
+        }
+
+        schema
+    }
+
     pub fn name(&self) -> ServiceName {
         self.service.name()
     }
@@ -103,8 +167,12 @@ impl PartialEq for Configuration {
             && self.color == other.color
             && self.server == other.server
             && self.service == other.service
+            && self.metrics == other.metrics
             && self.environment == other.environment
+            && self.environments == other.environments
             && self.deployment == other.deployment
+            && self.strict == other.strict
+            && self.redaction == other.redaction
     }
 }
 
@@ -118,6 +186,7 @@ impl From<Args> for Configuration {
             service_manager,
             system,
             port,
+            strict,
             verbose,
             ..
         } = args.clone();
@@ -143,8 +212,9 @@ impl From<Args> for Configuration {
             .maybe_verbosity(verbosity_level)
             .maybe_server(server)
             .maybe_environment(environment)
-            .color(color)
+            .color(color.unwrap_or_default())
             .service(service)
+            .strict(strict)
             .build()
     }
 }
@@ -444,7 +514,7 @@ fn environment_config() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(
         config,
         Configuration::builder()
-            .environment(Environment::Development)
+            .environment(Environment::DEVELOPMENT)
             .build()
     );
 
@@ -459,7 +529,7 @@ fn environment_config() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(
         config,
         Configuration::builder()
-            .environment(Environment::Production)
+            .environment(Environment::PRODUCTION)
             .build()
     );
 
@@ -474,9 +544,52 @@ fn environment_config() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(
         config,
         Configuration::builder()
-            .environment(Environment::Test)
+            .environment(Environment::TEST)
             .build()
     );
 
     Ok(())
 }
+
+#[test]
+fn schema() {
+    let schema = Configuration::schema();
+    let definitions = &schema["definitions"];
+
+    assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+
+    for name in [
+        "DeploymentConfig",
+        "SecurityConfig",
+        "LoggerConfig",
+        "OneOrMany",
+        "ConfigOrPreset",
+        "Environment",
+    ] {
+        assert!(definitions.get(name).is_some(), "missing {name}");
+    }
+
+    assert_eq!(
+        definitions["LoggerPreset"]["enum"],
+        serde_json::json!([
+            "error",
+            "rolling-info",
+            "rolling-debug",
+            "stdout",
+            "stderr",
+            "json-stdout",
+            "logfmt-stdout",
+            "pretty-stderr",
+            "journald",
+            "syslog",
+            "memory"
+        ])
+    );
+
+    assert_eq!(
+        definitions["OneOrMany"]["anyOf"][0]["items"]["$ref"],
+        "#/definitions/ConfigOrPreset"
+    );
+
+    assert_eq!(schema["properties"]["secret"]["type"], "string");
+}
@@ -16,6 +16,9 @@ pub enum DeploymentCommand {
     Pull,
     Push,
     Restart,
+    /// Show how each host's config, or each local container's, differs from
+    /// what would be sent to it.
+    Diff,
 }
 
 impl DeploymentCommand {
@@ -48,6 +51,12 @@ pub async fn exec_local_container_op(
             deployment_context.install_docker()?.run()?;
         }
         DeploymentCommand::List => deployment_context.list_containers()?.run()?,
+        DeploymentCommand::Diff => {
+            for (image, diff) in deployment_context.diff_containers()? {
+                println!("{image}:");
+                print!("{}", diff.to_colored_string());
+            }
+        }
         DeploymentCommand::Login => deployment_context.login()?.run()?,
         DeploymentCommand::Pull => {
             for image in deployment_context.images {
@@ -154,6 +163,12 @@ pub async fn exec_remote_container_op(
             }
         }
         DeploymentCommand::Push => {}
+        DeploymentCommand::Diff => {
+            for (host, diff) in deployment_context.diff_hosts()? {
+                println!("{host}:");
+                print!("{}", diff.to_colored_string());
+            }
+        }
         DeploymentCommand::Restart => {
             let path = deployment_context.emit_config()?;
             let from_path = path.to_string_lossy();
@@ -195,7 +210,7 @@ pub async fn exec_remote_container_op(
             let path = deployment_context.emit_config()?;
 
             let from_path = path.to_string_lossy();
-            let to_path = format!("{name}.container.json", name = controller.config.name());
+            let to_path = deployment_context.remote_config_path();
 
             for host in deployment_context.hosts {
                 host.send_file(&from_path, &to_path)?.run()?;
@@ -1,36 +1,52 @@
+use schemars::JsonSchema;
+use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct DeploymentConfig {
     pub artifacts: Option<Artifacts>,
     pub hosts: Vec<HostDefinition>,
     #[serde(default)]
     pub security: SecurityConfig,
+    /// Keys whose values, when resolved from secret references, are written
+    /// into emitted container config instead of the reference itself.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expose_secrets: Vec<String>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct Artifacts {
     pub containers: Option<Containers>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct Containers {
     pub registry: Option<Registry>,
     pub images: Vec<ImageDefinition>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Registry {
     pub account: String,
     pub host: String,
-    pub token: String,
+    #[serde(skip_serializing)]
+    #[schemars(with = "String")]
+    pub token: SecretString,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+impl PartialEq for Registry {
+    fn eq(&self, other: &Self) -> bool {
+        self.account == other.account
+            && self.host == other.host
+            && self.token.expose_secret() == other.token.expose_secret()
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct ImageDefinition {
     pub definition: String,
@@ -40,22 +56,34 @@ pub struct ImageDefinition {
     pub repo: String,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct HostDefinition {
     pub address: String,
     pub port: Option<u16>,
     pub user: Option<String>,
-    pub auth: Option<String>,
+    #[serde(default, skip_serializing)]
+    #[schemars(with = "Option<String>")]
+    pub auth: Option<SecretString>,
+}
+
+impl PartialEq for HostDefinition {
+    fn eq(&self, other: &Self) -> bool {
+        self.address == other.address
+            && self.port == other.port
+            && self.user == other.user
+            && self.auth.as_ref().map(ExposeSecret::expose_secret)
+                == other.auth.as_ref().map(ExposeSecret::expose_secret)
+    }
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 struct Security {
     certificates: Option<SecurityConfig>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize, PartialEq)]
 #[serde(tag = "type", rename_all = "kebab-case")]
 pub enum SecurityConfig {
     Acme {
@@ -68,5 +96,6 @@ pub enum SecurityConfig {
     #[default]
     Off,
     #[serde(untagged)]
+    #[schemars(skip)]
     Unknown(serde_json::Value),
 }
@@ -1,8 +1,12 @@
 use std::path::PathBuf;
 
 use figment::providers::Serialized;
+use secrecy::ExposeSecret;
 
-use crate::{shell, Configuration, Registry, ShellCommand, SupportControl};
+use crate::{
+    shell, ConfigDiff, ConfigSecrets, Configuration, Registry, ShellCommand, ShellCommandError,
+    SupportControl,
+};
 
 use super::{HostDeploymentContext, ImageDeploymentContext};
 
@@ -11,6 +15,8 @@ pub struct DeploymentContext {
     #[builder(default)]
     pub figment: figment::Figment,
     pub config: Configuration,
+    #[builder(default)]
+    pub secrets: ConfigSecrets,
     #[builder(default, into)]
     pub images: Vec<ImageDeploymentContext>,
     #[builder(default, into)]
@@ -62,6 +68,7 @@ impl DeploymentContext {
         Self::builder()
             .figment(figment)
             .config(config)
+            .secrets(controller.secrets.clone())
             .images(images)
             .hosts(hosts)
             .registry(registry)
@@ -72,21 +79,122 @@ impl DeploymentContext {
     pub fn emit_config(&self) -> crate::Result<PathBuf> {
         let path =
             std::env::temp_dir().join(format!("{name}.container.json", name = self.config.name()));
+        let contents = serde_json::to_string(&self.config_json()?)?;
+
+        std::fs::write(&path, contents).expect("Unable to write file");
 
+        Ok(path)
+    }
+
+    /// The merged configuration that gets sent to hosts.
+    pub fn config_json(&self) -> crate::Result<serde_json::Value> {
         let contents = serde_json::to_value(&self.config)?;
-        let all_configuration = self
+        let mut all_configuration = self
             .figment
             .clone()
             .merge(Serialized::from(contents, "default"))
             .extract::<serde_json::Value>()?;
 
-        let contents = serde_json::to_string(&all_configuration)?;
+        // Secrets stay as references unless the config allows them out.
+        // Skipped fields like host auth are put back the same way.
+        for key in self.secrets.keys() {
+            let (parent, field) = key.rsplit_once('.').unwrap_or(("", key));
+            let pointer = format!("/{}", parent.replace('.', "/"));
+            let value = match self.exposed_secrets().any(|exposed| exposed == key) {
+                true => self
+                    .secrets
+                    .get(key)
+                    .map(|secret| secret.expose_secret().into()),
+                false => self.secrets.reference(key).map(Into::into),
+            };
 
-        tracing::debug!(path = ?path, contents = ?contents, "writing container config file");
+            if let (Some(serde_json::Value::Object(parent)), Some(value)) = (
+                all_configuration.pointer_mut(pointer.trim_end_matches('/')),
+                value,
+            ) {
+                parent.insert(field.to_string(), value);
+            }
+        }
 
-        std::fs::write(&path, contents).expect("Unable to write file");
+        tracing::debug!(contents = ?all_configuration.to_string(), "emitting container config");
 
-        Ok(path)
+        Ok(all_configuration)
+    }
+
+    fn exposed_secrets(&self) -> impl Iterator<Item = &String> {
+        self.config
+            .deployment
+            .iter()
+            .flat_map(|deployment| &deployment.expose_secrets)
+    }
+
+    /// Where the emitted config is sent on each host.
+    pub fn remote_config_path(&self) -> String {
+        format!("{name}.container.json", name = self.config.name())
+    }
+
+    /// Compare the config each host has with what would be sent to it, with
+    /// secrets redacted. A host without a config file shows every key added.
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn diff_hosts(&self) -> crate::Result<Vec<(String, ConfigDiff)>> {
+        let emitted = self.config_json()?;
+        let mut diffs = Vec::new();
+
+        for host in &self.hosts {
+            let current = host.fetch_file(self.remote_config_path())?.output()?;
+
+            diffs.push((
+                host.host.address.clone(),
+                self.diff_config(&current, &emitted)?,
+            ));
+        }
+
+        Ok(diffs)
+    }
+
+    /// Compare the config each local container was started with to what
+    /// would be sent to it. Fails when a container isn't running.
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn diff_containers(&self) -> crate::Result<Vec<(String, ConfigDiff)>> {
+        let emitted = self.config_json()?;
+        let mut diffs = Vec::new();
+
+        for image in &self.images {
+            let source = image.config_source()?.output()?;
+            let current = match source.trim() {
+                "" => String::new(),
+                source => std::fs::read_to_string(source).map_err(|error| {
+                    ShellCommandError::FailedError(format!("unable to read {source}: {error}"))
+                })?,
+            };
+
+            diffs.push((
+                image.image.name.clone(),
+                self.diff_config(&current, &emitted)?,
+            ));
+        }
+
+        Ok(diffs)
+    }
+
+    /// Compare config file contents with emitted config, treating empty
+    /// contents as no config at all.
+    pub fn diff_config(
+        &self,
+        current: &str,
+        emitted: &serde_json::Value,
+    ) -> crate::Result<ConfigDiff> {
+        let current: serde_json::Value = match current.trim() {
+            "" => serde_json::Value::Null,
+            contents => serde_json::from_str(contents)?,
+        };
+        let redacted: Vec<&str> = self
+            .secrets
+            .keys()
+            .chain(self.exposed_secrets().map(String::as_str))
+            .collect();
+
+        Ok(ConfigDiff::between(&current, emitted)?.redact(&redacted))
     }
 
     #[tracing::instrument(skip(self), level = "trace")]
@@ -124,7 +232,7 @@ impl DeploymentContext {
             "docker login {host} -u {account} -p {token}",
             host = self.registry.host,
             account = self.registry.account,
-            token = self.registry.token
+            token = self.registry.token.expose_secret()
         ))
     }
 
@@ -139,3 +247,105 @@ impl From<&SupportControl> for DeploymentContext {
         Self::from_controller(controller)
     }
 }
+
+#[test]
+fn emitting_config_keeps_secret_references() {
+    use clap::Parser;
+
+    use crate::Args;
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file(
+            "support-kit.yaml",
+            r#"
+            secret: ${env:JWT_SECRET}
+            deployment:
+              hosts:
+                - address: example.com
+                  auth: ${env:DEPLOY_KEY}
+              expose-secrets: [secret]
+              artifacts:
+                containers:
+                  registry:
+                    account: esmevane
+                    host: ghcr.io
+                    token: ${env:GHCR_TOKEN}
+                  images: []
+        "#,
+        )?;
+
+        jail.set_env("JWT_SECRET", "hunter2");
+        jail.set_env("GHCR_TOKEN", "ghcr-token");
+        jail.set_env("DEPLOY_KEY", "deploy-key");
+
+        let args = Args::try_parse_from("app".split_whitespace()).unwrap();
+        let control = SupportControl::load_configuration(&args).unwrap();
+        let context = DeploymentContext::from_controller(&control);
+
+        let emitted: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(context.emit_config().unwrap()).unwrap())
+                .unwrap();
+
+        assert_eq!(emitted["secret"], "hunter2");
+        assert_eq!(
+            emitted["deployment"]["artifacts"]["containers"]["registry"]["token"],
+            "${env:GHCR_TOKEN}"
+        );
+        assert_eq!(
+            emitted["deployment"]["hosts"][0]["auth"],
+            "${env:DEPLOY_KEY}"
+        );
+        assert!(!emitted.to_string().contains("deploy-key"));
+        assert!(!format!("{context:?}").contains("ghcr-token"));
+
+        Ok(())
+    });
+}
+
+#[test]
+fn diffing_host_config_redacts_secrets() {
+    use clap::Parser;
+
+    use crate::Args;
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file(
+            "support-kit.yaml",
+            r#"
+            secret: ${env:JWT_SECRET}
+            verbosity: debug
+            deployment:
+              hosts: []
+              expose-secrets: [secret]
+        "#,
+        )?;
+
+        jail.set_env("JWT_SECRET", "hunter2");
+
+        let args = Args::try_parse_from("app".split_whitespace()).unwrap();
+        let control = SupportControl::load_configuration(&args).unwrap();
+        let context = DeploymentContext::from_controller(&control);
+        let emitted = context.config_json().unwrap();
+
+        let mut current = emitted.clone();
+        current["secret"] = "old-secret".into();
+        current["verbosity"] = "info".into();
+
+        let diff = context.diff_config(&current.to_string(), &emitted).unwrap();
+
+        assert_eq!(
+            diff.to_string(),
+            "secret: \"[redacted]\" -> \"[redacted]\"\nverbosity: \"info\" -> \"debug\"\n"
+        );
+        assert!(context
+            .diff_config("", &emitted)
+            .unwrap()
+            .changed("verbosity"));
+        assert!(context
+            .diff_config(&emitted.to_string(), &emitted)
+            .unwrap()
+            .is_empty());
+
+        Ok(())
+    });
+}
@@ -23,4 +23,50 @@ impl HostDeploymentContext {
             remote = to_path.as_ref(),
         ))
     }
+
+    /// Print a file from the host, or nothing if it isn't there. Fails when
+    /// the host can't be reached or the file can't be read.
+    pub fn fetch_file(&self, path: impl AsRef<str>) -> crate::Result<ShellCommand> {
+        let remote = quote(path.as_ref());
+
+        Ok(ShellCommand {
+            command: "ssh".into(),
+            args: vec![
+                "-i".into(),
+                self.host.auth.clone(),
+                format!(
+                    "{user}@{host}",
+                    user = self.host.user,
+                    host = self.host.address
+                ),
+                format!("[ ! -e {remote} ] || cat {remote}"),
+            ],
+        })
+    }
+}
+
+/// Single quote a word for the remote shell.
+fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+#[test]
+fn fetching_a_file_sends_one_remote_command() {
+    let context = HostDeploymentContext::builder()
+        .config(Configuration::default())
+        .host(HostDetails::builder().address("example.com").build())
+        .registry(Registry::default())
+        .build();
+    let command = context.fetch_file("it's here.json").unwrap();
+
+    assert_eq!(
+        command.command_and_args(),
+        vec![
+            "ssh",
+            "-i",
+            "~/.ssh/id_rsa",
+            "root@example.com",
+            r"[ ! -e 'it'\''s here.json' ] || cat 'it'\''s here.json'",
+        ]
+    );
 }
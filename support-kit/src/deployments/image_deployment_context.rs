@@ -98,6 +98,23 @@ impl ImageDeploymentContext {
         ))
     }
 
+    /// Print the host path mounted as the running container's config file.
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn config_source(&self) -> crate::Result<ShellCommand> {
+        Ok(ShellCommand {
+            command: "docker".into(),
+            args: vec![
+                "inspect".into(),
+                "--format".into(),
+                format!(
+                    r#"{{{{range .Mounts}}}}{{{{if eq .Destination "/{app_name}.json"}}}}{{{{.Source}}}}{{{{end}}}}{{{{end}}}}"#,
+                    app_name = self.config.name(),
+                ),
+                self.name(),
+            ],
+        })
+    }
+
     #[tracing::instrument(skip(self, config_path), level = "trace")]
     pub fn start(&self, config_path: impl Into<PathBuf>) -> crate::Result<ShellCommand> {
         let config_path = config_path.into();
@@ -129,7 +146,7 @@ impl ImageDeploymentContext {
             data = self.volume_name("data"),
         ));
 
-        println!("{:?}", operation);
+        tracing::debug!(command = ?operation, "starting container");
 
         operation
     }
@@ -11,8 +11,10 @@ impl PasswordControl {
         Self { params }
     }
 
+    /// Use the cheap test parameters in `test` and any environment that
+    /// inherits from it.
     pub fn from_config(config: Configuration) -> Self {
-        if let Some(Environment::Test) = config.environment {
+        if config.environment_lineage().contains(&Environment::TEST) {
             Self::test()
         } else {
             Self::default()
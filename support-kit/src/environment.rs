@@ -1,57 +1,175 @@
-use clap::ValueEnum;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
 use serde::{Deserialize, Serialize};
-use strum::{AsRefStr, Display, EnumIs, VariantArray};
-
-#[derive(
-    Clone,
-    Copy,
-    Debug,
-    Default,
-    Deserialize,
-    Display,
-    EnumIs,
-    VariantArray,
-    AsRefStr,
-    ValueEnum,
-    Serialize,
-    PartialEq,
-)]
-#[serde(rename_all = "kebab-case")]
-#[strum(serialize_all = "kebab-case")]
-pub enum Environment {
-    Test,
-    #[default]
-    Development,
-    Production,
-}
+use std::{borrow::Cow, collections::BTreeMap, fmt::Display, str::FromStr};
+
+use crate::EnvironmentError;
+
+/// A named environment, like `production` or `staging`. Names are lowercase
+/// ascii letters, digits, `-` and `_`, and start with a letter or digit, so
+/// they're safe to use in file names and environment variable prefixes.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct Environment(Cow<'static, str>);
 
 impl Environment {
+    pub const TEST: Environment = Environment(Cow::Borrowed("test"));
+    pub const DEVELOPMENT: Environment = Environment(Cow::Borrowed("development"));
+    pub const PRODUCTION: Environment = Environment(Cow::Borrowed("production"));
+
+    /// The well-known environments.
     pub fn all() -> Vec<Environment> {
-        Environment::VARIANTS.to_vec()
+        vec![Self::TEST, Self::DEVELOPMENT, Self::PRODUCTION]
+    }
+
+    pub fn new(name: impl Into<String>) -> Result<Self, EnvironmentError> {
+        let name = name.into();
+        let mut chars = name.chars();
+
+        let valid_start = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+        let valid_rest =
+            chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+
+        if valid_start && valid_rest {
+            Ok(Self(name.into()))
+        } else {
+            Err(EnvironmentError::InvalidName(name))
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_test(&self) -> bool {
+        self == &Self::TEST
+    }
+
+    pub fn is_development(&self) -> bool {
+        self == &Self::DEVELOPMENT
+    }
+
+    pub fn is_production(&self) -> bool {
+        self == &Self::PRODUCTION
+    }
+
+    /// This environment and every environment it inherits from, ordered from
+    /// the most distant ancestor to this environment, which is the order
+    /// their sources get layered in.
+    pub fn lineage(
+        &self,
+        environments: &BTreeMap<Environment, EnvironmentConfig>,
+    ) -> Result<Vec<Environment>, EnvironmentError> {
+        let mut lineage = vec![self.clone()];
+
+        while let Some(parent) = lineage
+            .last()
+            .and_then(|env| environments.get(env))
+            .and_then(|definition| definition.parent.clone())
+        {
+            if lineage.contains(&parent) {
+                lineage.push(parent);
+
+                let chain = lineage
+                    .iter()
+                    .map(Environment::name)
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+
+                return Err(EnvironmentError::Cycle(chain));
+            }
+
+            lineage.push(parent);
+        }
+
+        lineage.reverse();
+
+        Ok(lineage)
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::DEVELOPMENT
+    }
+}
+
+impl Display for Environment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AsRef<str> for Environment {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Environment {
+    type Err = EnvironmentError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::new(value)
     }
 }
 
 impl TryFrom<String> for Environment {
-    type Error = String;
+    type Error = EnvironmentError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        match value.as_str() {
-            "test" => Ok(Environment::Test),
-            "development" => Ok(Environment::Development),
-            "production" => Ok(Environment::Production),
-            _ => Err(value),
-        }
+        Self::new(value)
+    }
+}
+
+impl TryFrom<&str> for Environment {
+    type Error = EnvironmentError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::new(value)
     }
 }
 
+impl JsonSchema for Environment {
+    fn schema_name() -> Cow<'static, str> {
+        "Environment".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "pattern": "^[a-z0-9][a-z0-9_-]*$",
+            "examples": ["test", "development", "production"]
+        })
+    }
+}
+
+impl From<Environment> for String {
+    fn from(env: Environment) -> Self {
+        env.0.into_owned()
+    }
+}
+
+/// Settings for a single named environment.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize, PartialEq, bon::Builder)]
+#[serde(rename_all = "kebab-case")]
+pub struct EnvironmentConfig {
+    /// The environment this one inherits sources from, e.g. `staging` can
+    /// inherit from `production`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub parent: Option<Environment>,
+}
+
 #[test]
 fn all() {
     assert_eq!(
         Environment::all(),
         vec![
-            Environment::Test,
-            Environment::Development,
-            Environment::Production
+            Environment::TEST,
+            Environment::DEVELOPMENT,
+            Environment::PRODUCTION
         ]
     );
 }
@@ -60,15 +178,75 @@ fn all() {
 fn from_string() {
     assert_eq!(
         Environment::try_from("test".to_owned()),
-        Ok(Environment::Test)
+        Ok(Environment::TEST)
     );
     assert_eq!(
         Environment::try_from("development".to_owned()),
-        Ok(Environment::Development)
+        Ok(Environment::DEVELOPMENT)
     );
 
     assert_eq!(
         Environment::try_from("production".to_owned()),
-        Ok(Environment::Production)
+        Ok(Environment::PRODUCTION)
     );
+
+    assert_eq!(
+        Environment::try_from("preview-123").map(String::from),
+        Ok("preview-123".to_owned())
+    );
+
+    for invalid in ["", "Staging", "-staging", "us east", "../production"] {
+        assert_eq!(
+            Environment::try_from(invalid),
+            Err(EnvironmentError::InvalidName(invalid.to_owned()))
+        );
+    }
+}
+
+#[test]
+fn lineage() -> Result<(), Box<dyn std::error::Error>> {
+    let staging = Environment::new("staging")?;
+    let preview = Environment::new("preview")?;
+    let environments = BTreeMap::from([
+        (
+            staging.clone(),
+            EnvironmentConfig::builder()
+                .parent(Environment::PRODUCTION)
+                .build(),
+        ),
+        (
+            preview.clone(),
+            EnvironmentConfig::builder().parent(staging.clone()).build(),
+        ),
+    ]);
+
+    assert_eq!(
+        Environment::PRODUCTION.lineage(&environments)?,
+        vec![Environment::PRODUCTION]
+    );
+
+    assert_eq!(
+        preview.lineage(&environments)?,
+        vec![Environment::PRODUCTION, staging.clone(), preview.clone()]
+    );
+
+    let cyclic = BTreeMap::from([
+        (
+            staging.clone(),
+            EnvironmentConfig::builder().parent(preview.clone()).build(),
+        ),
+        (
+            preview.clone(),
+            EnvironmentConfig::builder().parent(staging.clone()).build(),
+        ),
+    ]);
+
+    assert_eq!(
+        staging.lineage(&cyclic),
+        Err(EnvironmentError::Cycle(
+            "staging -> preview -> staging".to_owned()
+        ))
+    );
+
+    Ok(())
 }
@@ -1,6 +1,8 @@
 use std::{io::Error, net::AddrParseError};
 use thiserror::Error;
 
+use crate::ConfigIssue;
+
 /// The auth token failed to verify.
 #[derive(thiserror::Error, Debug)]
 #[error("Token verification failed: {0}")]
@@ -49,12 +51,50 @@ pub enum ShellCommandError {
     ExecError(#[from] std::io::Error),
     #[error("malformed command, unable to parse: {0}")]
     MalformedError(String),
+    #[error("command failed: {0}")]
+    FailedError(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecretReferenceError {
+    #[error("unknown secret reference kind {0:?}, expected env, file or cmd")]
+    UnknownKind(String),
+    #[error("environment variable {0} is not set")]
+    MissingEnvVar(String),
+    #[error("unable to read secret file {0}: {1}")]
+    UnreadableFile(String, std::io::Error),
+    #[error("secret command failed: {0}")]
+    CommandFailed(#[from] ShellCommandError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigEncryptionError {
+    #[error("no config key found, looked in {0}")]
+    MissingKey(String),
+    #[error("invalid config key from {0}, expected 32 base64 encoded bytes")]
+    InvalidKey(String),
+    #[error("unable to decrypt `{0}`, is this the right key?")]
+    DecryptionFailed(String),
+    #[error("{0} is not an encrypted config file, expected a name like app.yaml.enc")]
+    NotEncrypted(String),
+    #[error("unable to write {0} config files, use yaml, json or toml")]
+    UnsupportedFormat(String),
+    #[error("unable to parse {0}: {1}")]
+    ParseError(String, String),
+    #[error("unable to access {0}: {1}")]
+    IoError(String, std::io::Error),
+    #[error("editor failed: {0}")]
+    EditorFailed(String),
 }
 
 #[derive(Debug, thiserror::Error)]
 #[error("network init error: {0}")]
 pub struct NetworkInitError(#[from] AddrParseError);
 
+#[derive(Debug, thiserror::Error)]
+#[error("unable to serve metrics: {0}")]
+pub struct MetricsError(#[from] std::io::Error);
+
 #[derive(Debug, thiserror::Error)]
 #[error("invalid service label: {0}")]
 pub struct InvalidServiceLabelError(#[from] std::io::Error);
@@ -85,6 +125,58 @@ pub enum SshError {
     InvalidPath(String),
 }
 
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum EnvironmentError {
+    #[error("invalid environment name {0:?}, expected lowercase letters, digits, '-' or '_'")]
+    InvalidName(String),
+    #[error("environment inherits from itself: {0}")]
+    Cycle(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoggingError {
+    #[error("logging hasn't been initialized")]
+    NotInitialized,
+    #[error("logging is already initialized: {0}")]
+    AlreadyInitialized(#[from] tracing::subscriber::SetGlobalDefaultError),
+    #[error("invalid log filter: {0}")]
+    InvalidFilter(#[from] tracing_subscriber::filter::ParseError),
+    #[error("unknown logging command {0:?}, expected status, filter, reset, verbosity or cycle")]
+    UnknownCommand(String),
+    #[error("invalid argument for {0}: {1:?}")]
+    InvalidArgument(String, String),
+    #[error("log socket error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("unable to build OTLP exporter: {0}")]
+    OtlpExporterError(Box<opentelemetry_otlp::ExporterBuildError>),
+    #[error("invalid OTLP header: {0}")]
+    InvalidOtlpHeader(String),
+    #[error("OTLP over gRPC has to start inside a tokio runtime")]
+    NoRuntime,
+    #[error("no memory logger is configured")]
+    NoLogBuffer,
+    #[error("unable to read log file {0}: {1}")]
+    UnreadableFile(String, std::io::Error),
+    #[error("no deployed host at {0}")]
+    UnknownHost(String),
+    #[error("unable to reach host: {0}")]
+    SshError(#[from] SshError),
+}
+
+impl From<opentelemetry_otlp::ExporterBuildError> for LoggingError {
+    fn from(err: opentelemetry_otlp::ExporterBuildError) -> Self {
+        LoggingError::OtlpExporterError(Box::new(err))
+    }
+}
+
+/// Every issue strict validation found, one per line.
+#[derive(Debug, thiserror::Error, PartialEq)]
+#[error(
+    "invalid configuration:\n{}",
+    .0.iter().map(|issue| format!("  {issue}")).collect::<Vec<_>>().join("\n")
+)]
+pub struct ConfigValidationError(pub Vec<ConfigIssue>);
+
 #[derive(Debug, thiserror::Error)]
 pub enum MissingDirError {
     #[error("missing home directory")]
@@ -102,14 +194,32 @@ pub enum SupportKitError {
     MissingDirError(#[from] MissingDirError),
 
     #[error("problem building config: {0}")]
-    ConfigBuildError(#[from] figment::Error),
+    ConfigBuildError(Box<figment::Error>),
+
+    #[error(transparent)]
+    ConfigValidationError(#[from] ConfigValidationError),
+
+    #[error("secret reference error: {0}")]
+    SecretReferenceError(#[from] SecretReferenceError),
+
+    #[error("config encryption error: {0}")]
+    ConfigEncryptionError(#[from] ConfigEncryptionError),
+
+    #[error("environment error: {0}")]
+    EnvironmentError(#[from] EnvironmentError),
 
     #[error("problem initializing network: {0}")]
     NetworkInitError(#[from] NetworkInitError),
 
+    #[error("metrics error: {0}")]
+    MetricsError(#[from] MetricsError),
+
     #[error("ssh error: {0}")]
     SshError(#[from] SshError),
 
+    #[error("logging error: {0}")]
+    LoggingError(#[from] LoggingError),
+
     #[error("serde error: {0}")]
     SerdeError(#[from] serde_json::Error),
 
@@ -125,3 +235,9 @@ pub enum SupportKitError {
     #[error("password error: {0}")]
     PasswordError(#[from] PasswordError),
 }
+
+impl From<figment::Error> for SupportKitError {
+    fn from(err: figment::Error) -> Self {
+        SupportKitError::ConfigBuildError(Box::new(err))
+    }
+}
@@ -9,3 +9,5 @@ pub use host_details::HostDetails;
 pub use host_session::HostSession;
 pub use ssh_connection::SshConnection;
 pub use ssh_session::SshSession;
+
+pub(crate) use ssh_session::expand_tilde;
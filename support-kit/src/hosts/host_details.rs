@@ -1,3 +1,5 @@
+use secrecy::ExposeSecret;
+
 use crate::HostDefinition;
 
 #[derive(Debug, Clone)]
@@ -35,7 +37,7 @@ impl From<HostDefinition> for HostDetails {
             .address(host.address)
             .maybe_port(host.port)
             .maybe_user(host.user)
-            .maybe_auth(host.auth)
+            .maybe_auth(host.auth.map(|auth| auth.expose_secret().to_string()))
             .build()
     }
 }
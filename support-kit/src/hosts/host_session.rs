@@ -25,4 +25,12 @@ impl HostSession {
     {
         self.session.run_cmd(cmd).await
     }
+
+    #[tracing::instrument(skip(self, cmd), level = "trace")]
+    pub async fn output<T>(&self, cmd: Vec<T>) -> Result<Vec<u8>, SshError>
+    where
+        T: AsRef<str>,
+    {
+        self.session.output(cmd).await
+    }
 }
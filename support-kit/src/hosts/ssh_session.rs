@@ -52,13 +52,8 @@ impl SshSession {
         T: AsRef<str>,
     {
         let mut channel = self.connection.channel_open_session().await?;
-        let command = command
-            .into_iter()
-            .map(|x| shell_escape::escape(x.as_ref().to_owned().into()))
-            .collect::<Vec<_>>()
-            .join(" ");
 
-        channel.exec(true, command).await?;
+        channel.exec(true, command_line(command)).await?;
 
         let mut code = None;
         let mut stdout = tokio::io::stdout();
@@ -103,12 +98,45 @@ impl SshSession {
 
         Ok(())
     }
+
+    /// Run a command, collecting what it prints instead of showing it.
+    #[tracing::instrument(skip(self, command), level = "debug")]
+    pub async fn output<T>(&self, command: Vec<T>) -> Result<Vec<u8>, SshError>
+    where
+        T: AsRef<str>,
+    {
+        let mut channel = self.connection.channel_open_session().await?;
+        let mut output = Vec::new();
+
+        channel.exec(true, command_line(command)).await?;
+
+        while let Some(msg) = channel.wait().await {
+            match msg {
+                ChannelMsg::Data { ref data } => output.extend_from_slice(data),
+                other => {
+                    tracing::trace!("unhandled channel message: {:?}", other);
+                }
+            }
+        }
+
+        channel.close().await?;
+
+        Ok(output)
+    }
+}
+
+fn command_line<T: AsRef<str>>(command: Vec<T>) -> String {
+    command
+        .into_iter()
+        .map(|x| shell_escape::escape(x.as_ref().to_owned().into()))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 // definitely an easier way to do this, but for now, cribbed from
 // https://stackoverflow.com/questions/54267608/expand-tilde-in-rust-path-idiomatically
 #[tracing::instrument(skip(path_user_input), level = "trace")]
-fn expand_tilde<P: AsRef<Path>>(path_user_input: P) -> Option<PathBuf> {
+pub(crate) fn expand_tilde<P: AsRef<Path>>(path_user_input: P) -> Option<PathBuf> {
     let path = path_user_input.as_ref();
     if !path.starts_with("~") {
         return Some(path.to_path_buf());
@@ -8,6 +8,7 @@ mod environment;
 mod errors;
 mod hosts;
 mod logs;
+mod metrics;
 mod network;
 mod service;
 mod shell;
@@ -21,10 +22,11 @@ pub use color::Color;
 pub use config::*;
 pub use deployments::*;
 pub use encryption::*;
-pub use environment::Environment;
+pub use environment::{Environment, EnvironmentConfig};
 pub use errors::*;
 pub use hosts::*;
 pub use logs::*;
+pub use metrics::*;
 pub use network::NetworkConfig;
 pub use service::*;
 pub use shell::*;
@@ -1,22 +1,53 @@
+mod log_age;
+mod log_buffer;
 mod log_file_config;
+mod log_file_writer;
+mod log_format;
+mod log_journald_config;
 mod log_level;
 mod log_level_config;
+mod log_level_range;
+mod log_memory_config;
+mod log_otlp_config;
+mod log_reader;
+mod log_redaction;
 mod log_rotation;
+mod log_size;
+mod log_syslog_config;
 mod log_target;
 mod logger_config;
 mod logger_preset;
 mod logging;
+mod logging_admin;
 
+pub use log_age::LogAge;
+pub use log_buffer::{LogBuffer, LogEvent, LogQuery, LogSpan};
 pub use log_file_config::LogFileConfig;
+pub use log_file_writer::LogFileWriter;
+pub use log_format::{LogFormat, LogFormatConfig, LogSpanEvents, LogTimestamp};
+pub use log_journald_config::LogJournaldConfig;
 pub use log_level::LogLevel;
 pub use log_level_config::LogLevelConfig;
+pub use log_level_range::{LogLevelRange, LogLevelRangeWriter};
+pub use log_memory_config::LogMemoryConfig;
+pub use log_otlp_config::{LogOtlpConfig, LogOtlpGuard, LogOtlpProtocol};
+pub use log_reader::LogReader;
+pub use log_redaction::{LogRedactionConfig, LogRedactor};
 pub use log_rotation::LogRotation;
+pub use log_size::LogSize;
+pub use log_syslog_config::{LogSyslogConfig, LogSyslogFacility, LogSyslogTransport};
 pub use log_target::LogTarget;
 pub use logger_config::LoggerConfig;
 pub use logger_preset::LoggerPreset;
-pub use logging::Logging;
+pub use logging::{
+    LoggerFilterHandle, Logging, LoggingGuard, LoggingHandle, ScopedLogging, VerbosityFilterHandle,
+};
+pub use logging_admin::{LoggingAdmin, LoggingCommand};
+
+use service_manager::ServiceManagerKind;
 
 use crate::{ConfigOrPreset, OneOrMany};
+use logger_config::parse_directives;
 
 pub type LoggerConfigOrPreset = ConfigOrPreset<LoggerConfig, LoggerPreset>;
 pub type LoggingConfig = OneOrMany<LoggerConfigOrPreset>;
@@ -32,6 +63,16 @@ impl LoggingConfig {
             Self::One(config_or_preset) => vec![config_or_preset.clone().into()],
         }
     }
+
+    /// The loggers to suggest for a service run by `manager`: the journal
+    /// under systemd, which captures stdout as plain text otherwise, and
+    /// nothing elsewhere.
+    pub fn suggested(manager: Option<ServiceManagerKind>) -> Option<Self> {
+        match manager {
+            Some(ServiceManagerKind::Systemd) => Some(Self::One(LoggerPreset::Journald.into())),
+            _ => None,
+        }
+    }
 }
 
 impl Default for LoggingConfig {
@@ -95,3 +136,23 @@ fn multiple_loggers_notation() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn suggested_loggers() {
+    assert_eq!(
+        LoggingConfig::suggested(Some(ServiceManagerKind::Systemd)),
+        Some(LoggingConfig::One(LoggerPreset::Journald.into()))
+    );
+    assert_eq!(
+        LoggingConfig::suggested(Some(ServiceManagerKind::Launchd)),
+        None
+    );
+    assert_eq!(LoggingConfig::suggested(None), None);
+    assert_eq!(
+        LoggerConfig::from(LoggerPreset::Journald),
+        LoggerConfig::builder()
+            .level(LogLevel::Error..LogLevel::Info)
+            .journald(LogJournaldConfig::default())
+            .build()
+    );
+}
@@ -0,0 +1,84 @@
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, fmt::Display, str::FromStr, time::Duration};
+
+/// How long to keep old log files, written with an `m`, `h`, `d` or `w`
+/// suffix, like `7d`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub struct LogAge(pub Duration);
+
+const UNITS: [(char, u64); 4] = [
+    ('w', 7 * 24 * 60 * 60),
+    ('d', 24 * 60 * 60),
+    ('h', 60 * 60),
+    ('m', 60),
+];
+
+impl LogAge {
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl FromStr for LogAge {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid age `{input}`, expected e.g. `7d`");
+        let input = input.trim();
+        let unit = input.chars().last().ok_or_else(invalid)?;
+        let (_, seconds) = UNITS
+            .iter()
+            .find(|(suffix, _)| *suffix == unit)
+            .ok_or_else(invalid)?;
+        let number = input[..input.len() - 1]
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| invalid())?;
+
+        number
+            .checked_mul(*seconds)
+            .map(|seconds| Self(Duration::from_secs(seconds)))
+            .ok_or_else(invalid)
+    }
+}
+
+impl Display for LogAge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let seconds = self.0.as_secs();
+        let (unit, per) = UNITS
+            .iter()
+            .find(|(_, per)| seconds >= *per && seconds.is_multiple_of(*per))
+            .unwrap_or(&('m', 60));
+
+        write!(f, "{}{unit}", seconds / per)
+    }
+}
+
+impl TryFrom<String> for LogAge {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<LogAge> for String {
+    fn from(age: LogAge) -> Self {
+        age.to_string()
+    }
+}
+
+impl JsonSchema for LogAge {
+    fn schema_name() -> Cow<'static, str> {
+        "LogAge".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "pattern": "^[0-9]+\\s*[mhdw]$"
+        })
+    }
+}
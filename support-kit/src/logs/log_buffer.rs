@@ -0,0 +1,431 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use owo_colors::{AnsiColors, OwoColorize, Stream};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan};
+
+use crate::TracingTarget;
+
+use super::{LogLevel, LogLevelRange};
+
+/// An event kept by a [`LogBuffer`], with its fields formatted as text.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct LogEvent {
+    pub timestamp: DateTime<Utc>,
+    pub level: LogLevel,
+    pub target: String,
+    pub message: Option<String>,
+    pub fields: BTreeMap<String, String>,
+    /// The spans the event happened in, outermost first.
+    pub spans: Vec<LogSpan>,
+}
+
+impl LogEvent {
+    /// Read a line written by a JSON file logger, if it is one.
+    pub fn from_json(line: &str) -> Option<Self> {
+        let json: serde_json::Value = serde_json::from_str(line).ok()?;
+        let timestamp = DateTime::parse_from_rfc3339(json["timestamp"].as_str()?).ok()?;
+        let level = match json["level"].as_str()? {
+            "ERROR" => LogLevel::Error,
+            "WARN" => LogLevel::Warn,
+            "INFO" => LogLevel::Info,
+            "DEBUG" => LogLevel::Debug,
+            _ => LogLevel::Trace,
+        };
+        let mut fields = json_fields(&json["fields"]);
+
+        Some(Self {
+            timestamp: timestamp.with_timezone(&Utc),
+            level,
+            target: json["target"].as_str().unwrap_or_default().to_string(),
+            message: fields.remove("message"),
+            fields,
+            spans: json["spans"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|span| {
+                    let mut fields = json_fields(span);
+
+                    LogSpan {
+                        name: fields.remove("name").unwrap_or_default(),
+                        fields,
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    /// One line with the level colored, when the terminal supports color.
+    pub fn to_colored_string(&self) -> String {
+        let color = match self.level {
+            LogLevel::Error => AnsiColors::Red,
+            LogLevel::Warn => AnsiColors::Yellow,
+            LogLevel::Info => AnsiColors::Green,
+            LogLevel::Debug => AnsiColors::Blue,
+            LogLevel::Trace => AnsiColors::Magenta,
+        };
+        let level = format!("{:>5}", format!("{:?}", self.level).to_uppercase());
+        let level = level.if_supports_color(Stream::Stdout, |level| level.color(color));
+        let mut line = format!(
+            "{timestamp} {level} ",
+            timestamp = self
+                .timestamp
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+        );
+
+        for span in &self.spans {
+            line.push_str(&format!(
+                "{name}{fields}:",
+                name = span
+                    .name
+                    .if_supports_color(Stream::Stdout, |name| name.bold()),
+                fields = braced(&span.fields)
+            ));
+        }
+
+        line.push_str(&format!(
+            "{target}: {message}",
+            target = self
+                .target
+                .if_supports_color(Stream::Stdout, |target| target.dimmed()),
+            message = self.message.as_deref().unwrap_or_default()
+        ));
+
+        for (name, value) in &self.fields {
+            line.push_str(&format!(
+                " {name}={value}",
+                name = name.if_supports_color(Stream::Stdout, |name| name.italic())
+            ));
+        }
+
+        line
+    }
+}
+
+/// A JSON object's values as text, with strings left unquoted.
+fn json_fields(value: &serde_json::Value) -> BTreeMap<String, String> {
+    value
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+fn braced(fields: &BTreeMap<String, String>) -> String {
+    match fields.is_empty() {
+        true => String::new(),
+        false => format!(
+            "{{{}}}",
+            fields
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct LogSpan {
+    pub name: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Picks events out of a [`LogBuffer`]. Every condition given has to match.
+#[derive(Clone, Debug, Default, bon::Builder)]
+pub struct LogQuery {
+    /// The least severe level to include.
+    pub level: Option<LogLevel>,
+    /// A target and the modules under it, like `my_app::db`.
+    #[builder(into)]
+    pub target: Option<String>,
+    /// The name of a span the event has to be in.
+    #[builder(into)]
+    pub span: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Values the event, or one of its spans, has to have.
+    #[builder(default, into)]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl LogQuery {
+    pub fn matches(&self, event: &LogEvent) -> bool {
+        let target = self.target.as_deref().is_none_or(|target| {
+            event
+                .target
+                .strip_prefix(target)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        });
+        let fields = self.fields.iter().all(|(name, value)| {
+            event.fields.get(name) == Some(value)
+                || event
+                    .spans
+                    .iter()
+                    .any(|span| span.fields.get(name) == Some(value))
+        });
+
+        let span = self
+            .span
+            .as_deref()
+            .is_none_or(|name| event.spans.iter().any(|span| span.name == name));
+
+        target
+            && span
+            && fields
+            && self.level.is_none_or(|level| event.level >= level)
+            && self.since.is_none_or(|since| event.timestamp >= since)
+            && self.until.is_none_or(|until| event.timestamp <= until)
+    }
+}
+
+/// The latest events from a memory logger, shared by every clone. New
+/// events are also broadcast to subscribers as they come in.
+#[derive(Clone, Debug)]
+pub struct LogBuffer {
+    events: Arc<Mutex<VecDeque<LogEvent>>>,
+    capacity: usize,
+    sender: broadcast::Sender<LogEvent>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+            sender: broadcast::channel(capacity.max(1)).0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Every event kept, oldest first.
+    pub fn events(&self) -> Vec<LogEvent> {
+        self.query(&LogQuery::default())
+    }
+
+    /// The events kept that match the query, oldest first.
+    pub fn query(&self, query: &LogQuery) -> Vec<LogEvent> {
+        self.events
+            .lock()
+            .map(|events| {
+                events
+                    .iter()
+                    .filter(|event| query.matches(event))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Receive events as they're logged. Subscribers that fall more than the
+    /// capacity behind miss the oldest ones.
+    pub fn subscribe(&self) -> broadcast::Receiver<LogEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut events) = self.events.lock() {
+            events.clear();
+        }
+    }
+
+    /// A logger keeping events in the given level range in this buffer.
+    pub fn layer(&self, levels: &LogLevelRange) -> TracingTarget {
+        use tracing_subscriber::Layer;
+
+        LogBufferLayer {
+            buffer: self.clone(),
+            levels: levels.clone(),
+        }
+        .boxed()
+    }
+
+    fn push(&self, event: LogEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if let Ok(mut events) = self.events.lock() {
+            if events.len() == self.capacity {
+                events.pop_front();
+            }
+
+            events.push_back(event.clone());
+        }
+
+        // Nobody listening isn't a problem.
+        let _ = self.sender.send(event);
+    }
+}
+
+struct LogBufferLayer {
+    buffer: LogBuffer,
+    levels: LogLevelRange,
+}
+
+/// A span's fields, kept in its extensions until its events need them.
+struct LogSpanFields(BTreeMap<String, String>);
+
+impl<S> tracing_subscriber::Layer<S> for LogBufferLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, context: Context<'_, S>) {
+        let mut fields = LogFields::default();
+        attrs.record(&mut fields);
+
+        if let Some(span) = context.span(id) {
+            span.extensions_mut().insert(LogSpanFields(fields.fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, context: Context<'_, S>) {
+        let Some(span) = context.span(id) else {
+            return;
+        };
+        let mut fields = LogFields::default();
+        values.record(&mut fields);
+
+        let mut extensions = span.extensions_mut();
+
+        if let Some(LogSpanFields(existing)) = extensions.get_mut::<LogSpanFields>() {
+            existing.extend(fields.fields);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, context: Context<'_, S>) {
+        let metadata = event.metadata();
+
+        if !self.levels.contains(metadata.level()) {
+            return;
+        }
+
+        let mut fields = LogFields::default();
+        event.record(&mut fields);
+
+        let spans = context
+            .event_scope(event)
+            .into_iter()
+            .flat_map(|scope| scope.from_root())
+            .map(|span| LogSpan {
+                name: span.name().to_string(),
+                fields: span
+                    .extensions()
+                    .get::<LogSpanFields>()
+                    .map(|LogSpanFields(fields)| fields.clone())
+                    .unwrap_or_default(),
+            })
+            .collect();
+
+        self.buffer.push(LogEvent {
+            timestamp: Utc::now(),
+            level: metadata.level().into(),
+            target: metadata.target().to_string(),
+            message: fields.message,
+            fields: fields.fields,
+            spans,
+        });
+    }
+}
+
+/// The `message` of an event, and every other field by name.
+#[derive(Default)]
+struct LogFields {
+    message: Option<String>,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for LogFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = Some(value.to_string()),
+            name => {
+                self.fields.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{value:?}"));
+    }
+}
+
+#[test]
+fn querying_buffered_events() {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let buffer = LogBuffer::new(3);
+    let levels = LogLevelRange::new(tracing::Level::ERROR, tracing::Level::DEBUG);
+    let mut receiver = buffer.subscribe();
+    let before = Utc::now();
+
+    tracing::subscriber::with_default(
+        tracing_subscriber::registry().with(buffer.layer(&levels)),
+        || {
+            tracing::info!(target: "my_app", "dropped once the buffer fills up");
+
+            tracing::info_span!("request", id = 7).in_scope(|| {
+                tracing::warn!(target: "my_app::db", rows = 0, "nothing found");
+                tracing::trace!("too verbose to keep");
+            });
+
+            tracing::error!(target: "other", user = "ada", "failed");
+            tracing::debug!(target: "my_app", "done");
+        },
+    );
+
+    let events = buffer.events();
+
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].message.as_deref(), Some("nothing found"));
+    assert_eq!(events[0].fields["rows"], "0");
+    assert_eq!(events[0].spans[0].name, "request");
+    assert_eq!(events[0].spans[0].fields["id"], "7");
+
+    let warnings = buffer.query(&LogQuery::builder().level(LogLevel::Warn).build());
+
+    assert_eq!(warnings.len(), 2);
+
+    let app = buffer.query(&LogQuery::builder().target("my_app").build());
+
+    assert_eq!(app.len(), 2);
+    assert_eq!(app[0].target, "my_app::db");
+
+    let in_request = buffer.query(
+        &LogQuery::builder()
+            .fields([("id".to_string(), "7".to_string())])
+            .since(before)
+            .build(),
+    );
+
+    assert_eq!(in_request, events[..1]);
+    assert!(buffer
+        .query(&LogQuery::builder().until(before).build())
+        .is_empty());
+
+    let received: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+
+    assert_eq!(received.len(), 4);
+    assert_eq!(received[3], events[2]);
+
+    buffer.clear();
+    assert!(buffer.events().is_empty());
+}
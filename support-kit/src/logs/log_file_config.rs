@@ -1,11 +1,17 @@
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tracing_appender::non_blocking::WorkerGuard;
 
-use crate::TracingTarget;
+use crate::{LoggingError, TracingTarget};
 
-use super::{LogRotation, LoggerConfig};
+use super::{
+    LogAge, LogFileWriter, LogFormat, LogFormatConfig, LogLevelRange, LogRotation, LogSize,
+    LogTimestamp,
+};
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, bon::Builder)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, bon::Builder)]
+#[serde(rename_all = "kebab-case")]
 pub struct LogFileConfig {
     #[builder(into)]
     pub directory: std::path::PathBuf,
@@ -13,38 +19,70 @@ pub struct LogFileConfig {
     pub name: String,
     #[builder(into)]
     pub rotation: Option<LogRotation>,
+    /// Start a new file once the current one would grow past this size.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<LogSize>,
+    /// How many files to keep, counting the one being written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_files: Option<usize>,
+    /// Remove rotated files older than this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<LogAge>,
+    /// Gzip files once they're rotated.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[builder(default)]
+    pub compress: bool,
+    /// The file name, with `{name}` and `{date}` filled in. Defaults to
+    /// `{name}.log.{date}`, or `{name}.log` when files never rotate by time.
+    /// Without `{date}`, files rotated by time are numbered like full ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub pattern: Option<String>,
 }
 
 impl LogFileConfig {
-    pub fn init_log_appender(&self, logger_config: &LoggerConfig) -> (TracingTarget, WorkerGuard) {
-        use tracing_appender::rolling::{daily, hourly, minutely, never};
-        use tracing_subscriber::{
-            fmt::{time::ChronoLocal, writer::MakeWriterExt},
-            Layer,
-        };
-
-        let directory = self.directory.clone();
-        let file_name_prefix = format!("{}.log", &self.name);
-
-        let (non_blocking, guard) =
-            tracing_appender::non_blocking(match self.rotation.clone().unwrap_or_default() {
-                LogRotation::Daily => daily(directory, file_name_prefix),
-                LogRotation::Hourly => hourly(directory, file_name_prefix),
-                LogRotation::PerMinute => minutely(directory, file_name_prefix),
-                LogRotation::Never => never(directory, file_name_prefix),
-            });
-
-        let logger = tracing_subscriber::fmt::layer()
-            .json()
-            .with_writer(
-                non_blocking
-                    .with_min_level(logger_config.min_tracing_level())
-                    .with_max_level(logger_config.max_tracing_level()),
-            )
-            .with_timer(ChronoLocal::default())
-            .boxed();
-
-        (logger, guard)
+    pub fn init_log_appender(
+        &self,
+        levels: &LogLevelRange,
+        output: &LogFormatConfig,
+    ) -> Result<(TracingTarget, WorkerGuard), LoggingError> {
+        let writer = LogFileWriter::new(self)?;
+        let (non_blocking, guard) = tracing_appender::non_blocking(writer);
+
+        let logger = output
+            .or(LogFormatConfig::builder()
+                .format(LogFormat::Json)
+                .timestamp(LogTimestamp::Local)
+                .ansi(false)
+                .build())
+            .layer(levels.writer(non_blocking));
+
+        Ok((logger, guard))
+    }
+
+    fn pattern(&self) -> &str {
+        match (&self.pattern, self.rotation.clone().unwrap_or_default()) {
+            (Some(pattern), _) => pattern,
+            (None, LogRotation::Never) => "{name}.log",
+            (None, _) => "{name}.log.{date}",
+        }
+    }
+
+    /// The name of the file for the given rotation period.
+    pub fn file_name(&self, period: &str) -> String {
+        self.pattern()
+            .replace("{name}", &self.name)
+            .replace("{date}", period)
+    }
+
+    /// Matches the names of every file this logger writes, rotated or not.
+    pub fn file_matcher(&self) -> Regex {
+        let pattern = regex::escape(self.pattern())
+            .replace(r"\{name\}", &regex::escape(&self.name))
+            .replace(r"\{date\}", "[0-9-]*");
+
+        Regex::new(&format!(r"^{pattern}(\.[0-9]+)?(\.gz)?$"))
+            .expect("escaped log file pattern is a valid regex")
     }
 }
 
@@ -73,3 +111,51 @@ where
             .build()
     }
 }
+
+#[test]
+fn size_and_retention_notation() -> Result<(), Box<dyn std::error::Error>> {
+    use std::time::Duration;
+
+    let config: LogFileConfig = serde_json::from_str(
+        r#"
+        {
+            "directory": "logs",
+            "name": "app",
+            "max-size": "10MB",
+            "max-files": 5,
+            "max-age": "7d",
+            "compress": true,
+            "pattern": "{name}-{date}.log"
+        }
+        "#,
+    )?;
+
+    assert_eq!(
+        config,
+        LogFileConfig::builder()
+            .directory("logs")
+            .name("app")
+            .max_size(LogSize(10 * 1024 * 1024))
+            .max_files(5)
+            .max_age(LogAge(Duration::from_secs(7 * 24 * 60 * 60)))
+            .compress(true)
+            .pattern("{name}-{date}.log")
+            .build()
+    );
+    assert_eq!(serde_json::to_value(&config)?["max-size"], "10MB");
+    assert_eq!(serde_json::to_value(&config)?["max-age"], "1w");
+    assert!(config.file_matcher().is_match("app-2024-01-01.log.3.gz"));
+    assert!(!config.file_matcher().is_match("app-2024-01-01.log.bak"));
+    assert!("10 parsecs".parse::<LogSize>().is_err());
+    assert_eq!("512".parse::<LogSize>()?, LogSize(512));
+    assert!(serde_json::from_str::<LogSize>("0").is_err());
+    assert!(serde_json::from_str::<LogSize>(r#""0MB""#).is_err());
+    assert!(format!("{max}GB", max = u64::MAX / 1024)
+        .parse::<LogSize>()
+        .is_err());
+    assert!(format!("{max}w", max = u64::MAX / 7)
+        .parse::<LogAge>()
+        .is_err());
+
+    Ok(())
+}
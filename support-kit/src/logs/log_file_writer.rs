@@ -0,0 +1,346 @@
+use chrono::{DateTime, Utc};
+use flate2::{write::GzEncoder, Compression};
+use regex::Regex;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::LogFileConfig;
+
+/// Writes a log file, starting a new one when the rotation period ends or
+/// the file would grow past `max-size`. Files rotated for size get a number
+/// after their name, counting up. Rotated files are compressed if asked, and
+/// pruned past `max-files` or `max-age`.
+#[derive(Debug)]
+pub struct LogFileWriter {
+    config: LogFileConfig,
+    matcher: Regex,
+    file: Option<File>,
+    path: PathBuf,
+    period: String,
+    size: u64,
+}
+
+impl LogFileWriter {
+    pub fn new(config: &LogFileConfig) -> io::Result<Self> {
+        Self::open_at(config, Utc::now())
+    }
+
+    fn open_at(config: &LogFileConfig, now: DateTime<Utc>) -> io::Result<Self> {
+        std::fs::create_dir_all(&config.directory)?;
+
+        let mut writer = Self {
+            matcher: config.file_matcher(),
+            config: config.clone(),
+            file: None,
+            path: PathBuf::new(),
+            period: String::new(),
+            size: 0,
+        };
+
+        writer.open(now)?;
+        writer.prune(now)?;
+
+        Ok(writer)
+    }
+
+    /// The file currently being written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn open(&mut self, now: DateTime<Utc>) -> io::Result<()> {
+        let period = self.config.rotation.clone().unwrap_or_default().period(now);
+        let path = self.config.directory.join(self.config.file_name(&period));
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        self.size = file.metadata()?.len();
+        self.file = Some(file);
+        self.path = path;
+        self.period = period;
+
+        Ok(())
+    }
+
+    fn write_at(&mut self, buf: &[u8], now: DateTime<Utc>) -> io::Result<usize> {
+        let period = self.config.rotation.clone().unwrap_or_default().period(now);
+        let full = self.config.max_size.is_some_and(|max_size| {
+            self.size > 0 && self.size + buf.len() as u64 > max_size.bytes()
+        });
+
+        if period != self.period || full {
+            // Number the rotated file when the next one would take its name,
+            // either because it's full or the pattern has no `{date}`.
+            let next = self.config.directory.join(self.config.file_name(&period));
+            let rotated = if next == self.path {
+                let numbered = self.next_numbered_path()?;
+
+                self.file = None;
+                std::fs::rename(&self.path, &numbered)?;
+                numbered
+            } else {
+                self.path.clone()
+            };
+
+            self.rotate(rotated, now)?;
+        }
+
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => return Err(io::Error::other("log file is closed")),
+        };
+        let written = file.write(buf)?;
+
+        self.size += written as u64;
+
+        Ok(written)
+    }
+
+    /// Finish with the given rotated file and open the next one.
+    fn rotate(&mut self, rotated: PathBuf, now: DateTime<Utc>) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+
+        if self.config.compress && rotated.exists() {
+            compress(&rotated)?;
+        }
+
+        self.open(now)?;
+        self.prune(now)
+    }
+
+    fn next_numbered_path(&self) -> io::Result<PathBuf> {
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let prefix = format!("{name}.");
+        let mut last = 0;
+
+        for entry in std::fs::read_dir(&self.config.directory)? {
+            let file_name = entry?.file_name().to_string_lossy().to_string();
+            let number = file_name
+                .strip_prefix(&prefix)
+                .map(|rest| rest.trim_end_matches(".gz"))
+                .and_then(|number| number.parse::<u32>().ok());
+
+            last = last.max(number.unwrap_or_default());
+        }
+
+        Ok(self.path.with_file_name(format!("{name}.{}", last + 1)))
+    }
+
+    /// Remove rotated files older than `max-age`, then the oldest rotated
+    /// files beyond `max-files`, which counts the file being written.
+    fn prune(&self, now: DateTime<Utc>) -> io::Result<()> {
+        if self.config.max_files.is_none() && self.config.max_age.is_none() {
+            return Ok(());
+        }
+
+        let mut rotated = Vec::new();
+
+        for entry in std::fs::read_dir(&self.config.directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if path != self.path && self.matcher.is_match(&name) {
+                rotated.push((entry.metadata()?.modified()?, name, path));
+            }
+        }
+
+        rotated.sort();
+
+        if let Some(max_age) = self.config.max_age {
+            let now = SystemTime::from(now);
+
+            rotated.retain(|(modified, _, path)| {
+                let expired = now
+                    .duration_since(*modified)
+                    .is_ok_and(|age| age > max_age.duration());
+
+                !(expired && std::fs::remove_file(path).is_ok())
+            });
+        }
+
+        if let Some(max_files) = self.config.max_files {
+            let keep = max_files.saturating_sub(1);
+            let excess = rotated.len().saturating_sub(keep);
+
+            for (_, _, path) in rotated.drain(..excess) {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_at(buf, Utc::now())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Replace a file with a gzipped copy, `name.gz`.
+fn compress(path: &Path) -> io::Result<()> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".gz");
+
+    let mut encoder = GzEncoder::new(File::create(&name)?, Compression::default());
+
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?;
+
+    std::fs::remove_file(path)
+}
+
+#[test]
+fn rotating_by_size() {
+    use super::LogSize;
+
+    figment::Jail::expect_with(|jail| {
+        let config = LogFileConfig::builder()
+            .directory(jail.directory().join("logs"))
+            .name("app")
+            .max_size(LogSize(16))
+            .max_files(3)
+            .compress(true)
+            .build();
+        let now = Utc::now();
+        let mut writer = LogFileWriter::open_at(&config, now).unwrap();
+
+        for line in ["one\n", "two\n", "three\n", "four\n", "five\n", "six\n"] {
+            writer
+                .write_at(format!("{line:>10}").as_bytes(), now)
+                .unwrap();
+        }
+
+        writer.flush().unwrap();
+
+        let mut files: Vec<String> = std::fs::read_dir(jail.directory().join("logs"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+
+        assert_eq!(files, vec!["app.log", "app.log.4.gz", "app.log.5.gz"]);
+
+        let mut contents = String::new();
+        let gzipped = File::open(jail.directory().join("logs/app.log.5.gz")).unwrap();
+
+        io::Read::read_to_string(&mut flate2::read::GzDecoder::new(gzipped), &mut contents)
+            .unwrap();
+
+        assert_eq!(contents.trim(), "five");
+        assert_eq!(
+            std::fs::read_to_string(writer.path()).unwrap().trim(),
+            "six"
+        );
+
+        Ok(())
+    });
+}
+
+#[test]
+fn rotating_by_time_with_retention() {
+    use std::time::Duration;
+
+    use super::{LogAge, LogRotation};
+
+    figment::Jail::expect_with(|jail| {
+        let directory = jail.directory().join("logs");
+        let config = LogFileConfig::builder()
+            .directory(&directory)
+            .name("app")
+            .rotation(LogRotation::Daily)
+            .pattern("{date}-{name}.txt")
+            .max_age(LogAge(Duration::from_secs(7 * 24 * 60 * 60)))
+            .build();
+        let first = "2024-01-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let second = "2024-01-02T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let later = "2024-01-20T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut writer = LogFileWriter::open_at(&config, first).unwrap();
+
+        writer.write_at(b"first\n", first).unwrap();
+        writer.write_at(b"second\n", second).unwrap();
+
+        assert_eq!(writer.path(), directory.join("2024-01-02-app.txt"));
+        assert_eq!(
+            std::fs::read_to_string(directory.join("2024-01-01-app.txt")).unwrap(),
+            "first\n"
+        );
+
+        jail.create_file("logs/unrelated.txt", "kept")?;
+
+        for name in ["2024-01-01-app.txt", "2024-01-02-app.txt", "unrelated.txt"] {
+            File::options()
+                .write(true)
+                .open(directory.join(name))
+                .unwrap()
+                .set_modified(SystemTime::from(second))
+                .unwrap();
+        }
+
+        writer.write_at(b"later\n", later).unwrap();
+
+        assert!(!directory.join("2024-01-01-app.txt").exists());
+        assert!(!directory.join("2024-01-02-app.txt").exists());
+        assert!(directory.join("2024-01-20-app.txt").exists());
+        assert!(directory.join("unrelated.txt").exists());
+
+        Ok(())
+    });
+}
+
+#[test]
+fn rotating_by_time_without_date() {
+    use super::LogRotation;
+
+    figment::Jail::expect_with(|jail| {
+        let directory = jail.directory().join("logs");
+        let config = LogFileConfig::builder()
+            .directory(&directory)
+            .name("app")
+            .rotation(LogRotation::Daily)
+            .pattern("{name}.log")
+            .compress(true)
+            .build();
+        let first = "2024-01-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let mut writer = LogFileWriter::open_at(&config, first).unwrap();
+
+        for (day, line) in ["first", "second", "third"].iter().enumerate() {
+            let now = first + chrono::Duration::days(day as i64);
+
+            writer
+                .write_at(format!("{line}\n").as_bytes(), now)
+                .unwrap();
+        }
+
+        let mut files: Vec<String> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+
+        assert_eq!(files, vec!["app.log", "app.log.1.gz", "app.log.2.gz"]);
+
+        let mut contents = String::new();
+        let gzipped = File::open(directory.join("app.log.1.gz")).unwrap();
+
+        io::Read::read_to_string(&mut flate2::read::GzDecoder::new(gzipped), &mut contents)
+            .unwrap();
+
+        assert_eq!(contents, "first\n");
+
+        Ok(())
+    });
+}
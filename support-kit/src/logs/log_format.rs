@@ -0,0 +1,252 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::fmt::{
+    format::{FmtSpan, Writer},
+    time::{ChronoLocal, ChronoUtc, FormatTime, Uptime},
+    MakeWriter,
+};
+
+use crate::TracingTarget;
+
+/// How each log line is laid out.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    /// Multiple lines per event, for reading while developing.
+    Pretty,
+    /// One short line per event.
+    Compact,
+    /// One line per event, with the fields of every span it's in.
+    #[default]
+    Full,
+    /// One JSON object per line.
+    Json,
+    /// `key=value` pairs, one line per event. Timestamps are always UTC, and
+    /// span events and thread IDs aren't logged.
+    Logfmt,
+}
+
+/// How timestamps are written.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogTimestamp {
+    /// RFC 3339 in the local timezone.
+    Local,
+    /// RFC 3339 in UTC.
+    #[default]
+    Utc,
+    /// Time since the logger started.
+    Uptime,
+    /// No timestamp.
+    None,
+}
+
+/// Which span lifecycle events are logged.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogSpanEvents {
+    #[default]
+    None,
+    New,
+    Close,
+    Active,
+    Full,
+}
+
+impl From<LogSpanEvents> for FmtSpan {
+    fn from(events: LogSpanEvents) -> Self {
+        match events {
+            LogSpanEvents::None => FmtSpan::NONE,
+            LogSpanEvents::New => FmtSpan::NEW,
+            LogSpanEvents::Close => FmtSpan::CLOSE,
+            LogSpanEvents::Active => FmtSpan::ACTIVE,
+            LogSpanEvents::Full => FmtSpan::FULL,
+        }
+    }
+}
+
+/// The layout of a logger's output. Anything not set falls back to the
+/// logger's defaults: full lines with UTC timestamps for the console, and
+/// JSON with local timestamps for files. ANSI colour follows `color` for the
+/// console and is off for files.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, bon::Builder)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogFormatConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<LogFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ansi: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_events: Option<LogSpanEvents>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_ids: Option<bool>,
+    /// Include the file and line each event came from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_line: Option<bool>,
+    /// Include the target (usually the module path) of each event.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<LogTimestamp>,
+}
+
+impl LogFormatConfig {
+    /// Fill in anything not set from `defaults`.
+    pub fn or(&self, defaults: Self) -> Self {
+        Self {
+            format: self.format.or(defaults.format),
+            ansi: self.ansi.or(defaults.ansi),
+            span_events: self.span_events.or(defaults.span_events),
+            thread_ids: self.thread_ids.or(defaults.thread_ids),
+            file_line: self.file_line.or(defaults.file_line),
+            target: self.target.or(defaults.target),
+            timestamp: self.timestamp.or(defaults.timestamp),
+        }
+    }
+
+    /// Build a logger writing to `writer` in this layout.
+    pub fn layer<W>(&self, writer: W) -> TracingTarget
+    where
+        W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+    {
+        use tracing_subscriber::Layer;
+
+        let ansi = self.ansi.unwrap_or_default();
+        let file_line = self.file_line.unwrap_or_default();
+        let target = self.target.unwrap_or(true);
+        let timestamp = self.timestamp.unwrap_or_default();
+
+        if let Some(LogFormat::Logfmt) = self.format {
+            return tracing_logfmt::builder()
+                .with_target(target)
+                .with_location(file_line)
+                .with_timestamp(timestamp != LogTimestamp::None)
+                .with_ansi_color(ansi)
+                .layer()
+                .with_writer(writer)
+                .boxed();
+        }
+
+        let layer = tracing_subscriber::fmt::layer()
+            .with_writer(writer)
+            .with_ansi(ansi)
+            .with_target(target)
+            .with_thread_ids(self.thread_ids.unwrap_or_default())
+            .with_file(file_line)
+            .with_line_number(file_line)
+            .with_span_events(self.span_events.unwrap_or_default().into());
+
+        // Each layout and timer is its own type, so box each combination.
+        macro_rules! with_timer {
+            ($layer:expr) => {
+                match LogTimer::new(timestamp) {
+                    Some(timer) => $layer.with_timer(timer).boxed(),
+                    None => $layer.without_time().boxed(),
+                }
+            };
+        }
+
+        match self.format.unwrap_or_default() {
+            LogFormat::Pretty => with_timer!(layer.pretty()),
+            LogFormat::Compact => with_timer!(layer.compact()),
+            LogFormat::Json => with_timer!(layer.json()),
+            LogFormat::Full | LogFormat::Logfmt => with_timer!(layer),
+        }
+    }
+}
+
+/// The timer for a [`LogTimestamp`].
+enum LogTimer {
+    Local(ChronoLocal),
+    Utc(ChronoUtc),
+    Uptime(Uptime),
+}
+
+impl LogTimer {
+    fn new(timestamp: LogTimestamp) -> Option<Self> {
+        match timestamp {
+            LogTimestamp::Local => Some(Self::Local(ChronoLocal::rfc_3339())),
+            LogTimestamp::Utc => Some(Self::Utc(ChronoUtc::rfc_3339())),
+            LogTimestamp::Uptime => Some(Self::Uptime(Uptime::default())),
+            LogTimestamp::None => None,
+        }
+    }
+}
+
+impl FormatTime for LogTimer {
+    fn format_time(&self, writer: &mut Writer<'_>) -> std::fmt::Result {
+        match self {
+            Self::Local(timer) => timer.format_time(writer),
+            Self::Utc(timer) => timer.format_time(writer),
+            Self::Uptime(timer) => timer.format_time(writer),
+        }
+    }
+}
+
+#[test]
+fn formatting_log_lines() {
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let capture = |config: LogFormatConfig| {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::registry().with(config.layer(move || writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(answer = 42, "logged");
+        });
+
+        let output = captured.0.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    };
+
+    let json = capture(
+        LogFormatConfig::builder()
+            .format(LogFormat::Json)
+            .timestamp(LogTimestamp::None)
+            .target(false)
+            .build(),
+    );
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(json["fields"]["answer"], 42);
+    assert_eq!(json["level"], "INFO");
+    assert!(json.get("timestamp").is_none());
+    assert!(json.get("target").is_none());
+
+    let logfmt = capture(
+        LogFormatConfig::builder()
+            .format(LogFormat::Logfmt)
+            .timestamp(LogTimestamp::None)
+            .build(),
+    );
+
+    assert!(logfmt.starts_with("level=info target="));
+    assert!(logfmt.contains("answer=42"));
+
+    let compact = capture(
+        LogFormatConfig::builder()
+            .format(LogFormat::Compact)
+            .ansi(false)
+            .file_line(true)
+            .build(),
+    );
+
+    assert!(compact.contains("log_format.rs:"));
+    assert!(!compact.contains('\u{1b}'));
+}
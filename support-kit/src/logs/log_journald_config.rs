@@ -0,0 +1,88 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Configuration, LoggingError, TracingTarget};
+
+use super::LogLevelRange;
+
+/// Writes events to the systemd journal over its native protocol, keeping
+/// each event's fields as journal fields.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, bon::Builder)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogJournaldConfig {
+    /// The `SYSLOG_IDENTIFIER` of each entry. Defaults to the service name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub identifier: Option<String>,
+    /// Added to the name of every field but `message`, since the journal
+    /// reserves some names. Defaults to `F`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub field_prefix: Option<String>,
+}
+
+impl LogJournaldConfig {
+    /// Build a logger writing to the journal, failing if its socket isn't
+    /// there to write to.
+    pub fn init_journald_logger(
+        &self,
+        config: &Configuration,
+        levels: &LogLevelRange,
+    ) -> Result<TracingTarget, LoggingError> {
+        use tracing_subscriber::{filter::filter_fn, Layer};
+
+        let levels = levels.clone();
+
+        Ok(tracing_journald::layer()?
+            .with_syslog_identifier(self.identifier(config))
+            .with_field_prefix(self.field_prefix())
+            .with_filter(filter_fn(move |metadata| levels.contains(metadata.level())))
+            .boxed())
+    }
+
+    fn identifier(&self, config: &Configuration) -> String {
+        match &self.identifier {
+            Some(identifier) => identifier.clone(),
+            None => config.name().to_string(),
+        }
+    }
+
+    /// An empty prefix leaves field names as they are.
+    fn field_prefix(&self) -> Option<String> {
+        match self.field_prefix.as_deref() {
+            None => Some("F".into()),
+            Some("") => None,
+            Some(prefix) => Some(prefix.into()),
+        }
+    }
+}
+
+#[test]
+fn journald_identifier_and_field_prefix() {
+    use crate::{LogLevel, LoggerConfig, ServiceConfig};
+
+    let config = Configuration::builder()
+        .service(ServiceConfig::builder().name("journal-app").build())
+        .build();
+    let journald = LogJournaldConfig::default();
+
+    assert_eq!(journald.identifier(&config), "journal-app");
+    assert_eq!(journald.field_prefix().as_deref(), Some("F"));
+
+    let journald = LogJournaldConfig::builder()
+        .identifier("custom")
+        .field_prefix("")
+        .build();
+
+    assert_eq!(journald.identifier(&config), "custom");
+    assert_eq!(journald.field_prefix(), None);
+
+    if !std::path::Path::new("/run/systemd/journal/socket").exists() {
+        let levels = LogLevelRange::from(&LoggerConfig::builder().level(LogLevel::Info).build());
+
+        assert!(matches!(
+            journald.init_journald_logger(&config, &levels),
+            Err(LoggingError::IoError(_))
+        ));
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::{Arc, RwLock};
+use tracing::{Level, Metadata};
+use tracing_subscriber::fmt::{
+    writer::{EitherWriter, OptionalWriter},
+    MakeWriter,
+};
+
+use super::LoggerConfig;
+
+/// The range of levels a logger writes, shared between the logger and the
+/// [`LoggingHandle`](super::LoggingHandle) so it can change while running.
+#[derive(Clone, Debug)]
+pub struct LogLevelRange(Arc<RwLock<(Level, Level)>>);
+
+impl LogLevelRange {
+    pub fn new(min: Level, max: Level) -> Self {
+        Self(Arc::new(RwLock::new((min, max))))
+    }
+
+    pub fn set(&self, min: Level, max: Level) {
+        if let Ok(mut range) = self.0.write() {
+            *range = (min, max);
+        }
+    }
+
+    pub fn get(&self) -> (Level, Level) {
+        self.0
+            .read()
+            .map(|range| *range)
+            .unwrap_or((Level::ERROR, Level::TRACE))
+    }
+
+    /// Whether an event at the given level falls in this range. As with
+    /// tracing's own ordering, more verbose levels are greater.
+    pub fn contains(&self, level: &Level) -> bool {
+        let (min, max) = self.get();
+
+        level >= &min && level <= &max
+    }
+
+    /// Wrap a writer so it only writes events in this range.
+    pub fn writer<W>(&self, inner: W) -> LogLevelRangeWriter<W> {
+        LogLevelRangeWriter {
+            inner,
+            range: self.clone(),
+        }
+    }
+}
+
+impl From<&LoggerConfig> for LogLevelRange {
+    fn from(logger_config: &LoggerConfig) -> Self {
+        Self::new(
+            logger_config.min_tracing_level(),
+            logger_config.max_tracing_level(),
+        )
+    }
+}
+
+/// A writer that skips events outside of a [`LogLevelRange`].
+#[derive(Clone, Debug)]
+pub struct LogLevelRangeWriter<W> {
+    inner: W,
+    range: LogLevelRange,
+}
+
+impl<'a, W> MakeWriter<'a> for LogLevelRangeWriter<W>
+where
+    W: MakeWriter<'a>,
+{
+    type Writer = OptionalWriter<W::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        EitherWriter::A(self.inner.make_writer())
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        if self.range.contains(meta.level()) {
+            EitherWriter::A(self.inner.make_writer_for(meta))
+        } else {
+            EitherWriter::none()
+        }
+    }
+}
+
+#[test]
+fn changing_level_ranges() {
+    let range = LogLevelRange::new(Level::INFO, Level::TRACE);
+
+    assert!(range.contains(&Level::DEBUG));
+    assert!(!range.contains(&Level::WARN));
+
+    let shared = range.clone();
+    shared.set(Level::ERROR, Level::WARN);
+
+    assert!(range.contains(&Level::WARN));
+    assert!(!range.contains(&Level::DEBUG));
+}
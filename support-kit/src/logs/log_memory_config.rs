@@ -0,0 +1,31 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::LogBuffer;
+
+/// Keeps the latest events in memory, for tests to assert on or a service to
+/// show, through [`SupportControl::log_buffer`](crate::SupportControl::log_buffer).
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, bon::Builder)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogMemoryConfig {
+    /// How many events to keep, dropping the oldest first.
+    #[serde(default = "default_capacity")]
+    #[builder(default = default_capacity())]
+    pub capacity: usize,
+}
+
+fn default_capacity() -> usize {
+    1000
+}
+
+impl Default for LogMemoryConfig {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl LogMemoryConfig {
+    pub fn init_log_buffer(&self) -> LogBuffer {
+        LogBuffer::new(self.capacity)
+    }
+}
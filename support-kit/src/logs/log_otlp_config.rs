@@ -0,0 +1,317 @@
+use std::collections::{BTreeMap, HashMap};
+
+use opentelemetry::{trace::TracerProvider, KeyValue};
+use opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge;
+use opentelemetry_otlp::{
+    tonic_types::metadata::MetadataMap, LogExporter, Protocol, SpanExporter, WithExportConfig,
+    WithHttpConfig, WithTonicConfig,
+};
+use opentelemetry_sdk::{
+    logs::SdkLoggerProvider,
+    trace::{Sampler, SdkTracerProvider},
+    Resource,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Configuration, LoggingError, TracingTarget};
+
+use super::LogLevelRange;
+
+/// Targets the exporters log to themselves, which would otherwise be
+/// exported in turn.
+const EXPORTER_TARGETS: &[&str] = &["h2", "hyper", "opentelemetry", "reqwest", "tonic", "tower"];
+
+/// How spans and events get to the collector.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogOtlpProtocol {
+    #[default]
+    Grpc,
+    HttpProtobuf,
+}
+
+/// Exports spans as traces and events as logs to an OpenTelemetry collector.
+/// The service name and environment are added to the resource attributes.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, bon::Builder)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogOtlpConfig {
+    /// The collector's base URL. Defaults to `OTEL_EXPORTER_OTLP_ENDPOINT`,
+    /// then to the protocol's usual port on localhost.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    #[builder(default)]
+    pub protocol: LogOtlpProtocol,
+    /// Sent along with every export, like an `authorization` header.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[builder(default, into)]
+    pub headers: BTreeMap<String, String>,
+    /// The share of traces to keep, from 0 to 1. Spans follow their parent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<f64>,
+    /// Extra resource attributes, like `deployment.region`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[builder(default, into)]
+    pub resource: BTreeMap<String, String>,
+}
+
+impl LogOtlpConfig {
+    /// Build a logger exporting to the collector, along with a guard that
+    /// flushes the exporters when dropped. gRPC exporters have to be built
+    /// inside a tokio runtime.
+    pub fn init_otlp_exporter(
+        &self,
+        config: &Configuration,
+        levels: &LogLevelRange,
+    ) -> Result<(TracingTarget, LogOtlpGuard), LoggingError> {
+        use tracing_subscriber::{filter::filter_fn, Layer};
+
+        if self.protocol == LogOtlpProtocol::Grpc && tokio::runtime::Handle::try_current().is_err()
+        {
+            return Err(LoggingError::NoRuntime);
+        }
+
+        let resource = self.resource(config);
+        let (spans, logs) = match self.protocol {
+            LogOtlpProtocol::Grpc => (
+                self.grpc(SpanExporter::builder().with_tonic())?.build()?,
+                self.grpc(LogExporter::builder().with_tonic())?.build()?,
+            ),
+            LogOtlpProtocol::HttpProtobuf => (
+                self.http(SpanExporter::builder().with_http(), "traces")
+                    .build()?,
+                self.http(LogExporter::builder().with_http(), "logs")
+                    .build()?,
+            ),
+        };
+
+        let traces = SdkTracerProvider::builder()
+            .with_batch_exporter(spans)
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                self.sampling.unwrap_or(1.0),
+            ))))
+            .with_resource(resource.clone())
+            .build();
+        let logs = SdkLoggerProvider::builder()
+            .with_batch_exporter(logs)
+            .with_resource(resource)
+            .build();
+
+        let levels = levels.clone();
+        let logger = tracing_opentelemetry::layer()
+            .with_tracer(traces.tracer(config.name().to_string()))
+            .and_then(OpenTelemetryTracingBridge::new(&logs))
+            .with_filter(filter_fn(move |metadata| {
+                let target = metadata.target();
+                let exporter = EXPORTER_TARGETS.iter().any(|name| {
+                    target
+                        .strip_prefix(name)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+                });
+
+                levels.contains(metadata.level()) && !exporter
+            }))
+            .boxed();
+
+        Ok((logger, LogOtlpGuard { traces, logs }))
+    }
+
+    fn resource(&self, config: &Configuration) -> Resource {
+        let environment = config.environment.clone().unwrap_or_default();
+
+        Resource::builder()
+            .with_service_name(config.name().to_string())
+            .with_attribute(KeyValue::new(
+                "deployment.environment.name",
+                environment.to_string(),
+            ))
+            .with_attributes(
+                self.resource
+                    .iter()
+                    .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+            )
+            .build()
+    }
+
+    fn headers(&self) -> HashMap<String, String> {
+        self.headers.clone().into_iter().collect()
+    }
+
+    fn grpc<B: WithExportConfig + WithTonicConfig>(&self, builder: B) -> Result<B, LoggingError> {
+        let headers = http::HeaderMap::try_from(&self.headers())
+            .map_err(|error| LoggingError::InvalidOtlpHeader(error.to_string()))?;
+        let builder = builder.with_metadata(MetadataMap::from_headers(headers));
+
+        Ok(match &self.endpoint {
+            Some(endpoint) => builder.with_endpoint(endpoint),
+            None => builder,
+        })
+    }
+
+    fn http<B: WithExportConfig + WithHttpConfig>(&self, builder: B, signal: &str) -> B {
+        let builder = builder
+            .with_protocol(Protocol::HttpBinary)
+            .with_headers(self.headers());
+
+        // The HTTP exporters only add the signal's path to endpoints from
+        // the environment.
+        match &self.endpoint {
+            Some(endpoint) => builder.with_endpoint(format!(
+                "{endpoint}/v1/{signal}",
+                endpoint = endpoint.trim_end_matches('/')
+            )),
+            None => builder,
+        }
+    }
+}
+
+/// Flushes and stops an OTLP logger's exporters when dropped.
+#[derive(Debug)]
+pub struct LogOtlpGuard {
+    traces: SdkTracerProvider,
+    logs: SdkLoggerProvider,
+}
+
+impl Drop for LogOtlpGuard {
+    fn drop(&mut self) {
+        if let Err(error) = self.logs.shutdown() {
+            tracing::warn!(%error, "unable to flush OTLP logs");
+        }
+
+        if let Err(error) = self.traces.shutdown() {
+            tracing::warn!(%error, "unable to flush OTLP traces");
+        }
+    }
+}
+
+/// Every request a test collector got, as the path, headers and body.
+#[cfg(test)]
+pub(crate) type CollectorRequests =
+    std::sync::Arc<std::sync::Mutex<Vec<(String, String, Vec<u8>)>>>;
+
+/// An OTLP/HTTP collector on a local port that accepts everything.
+#[cfg(test)]
+pub(crate) fn test_collector() -> (std::net::SocketAddr, CollectorRequests) {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let requests = CollectorRequests::default();
+    let received = requests.clone();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let received = received.clone();
+
+            std::thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut stream = stream;
+
+                loop {
+                    let mut request_line = String::new();
+
+                    if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                        return;
+                    }
+
+                    let mut headers = String::new();
+                    let mut length = 0;
+
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+
+                        if line.trim().is_empty() {
+                            break;
+                        }
+
+                        let line = line.to_lowercase();
+
+                        if let Some(value) = line.strip_prefix("content-length:") {
+                            length = value.trim().parse().unwrap();
+                        }
+
+                        headers.push_str(&line);
+                    }
+
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).unwrap();
+
+                    let path = request_line.split_whitespace().nth(1).unwrap().to_string();
+                    received.lock().unwrap().push((path, headers, body));
+
+                    stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-type: application/x-protobuf\r\ncontent-length: 0\r\n\r\n")
+                        .unwrap();
+                }
+            });
+        }
+    });
+
+    (address, requests)
+}
+
+#[test]
+fn exporting_to_a_collector() {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::{Environment, LogLevel, LoggerConfig, ServiceConfig};
+
+    let (address, requests) = test_collector();
+
+    let otlp = LogOtlpConfig::builder()
+        .endpoint(format!("http://{address}/"))
+        .protocol(LogOtlpProtocol::HttpProtobuf)
+        .headers([("x-team".to_string(), "ops".to_string())])
+        .resource([("deployment.region".to_string(), "eu-west".to_string())])
+        .build();
+    let config = Configuration::builder()
+        .service(ServiceConfig::builder().name("otlp-app").build())
+        .environment(Environment::PRODUCTION)
+        .build();
+    let logger = LoggerConfig::builder()
+        .level(LogLevel::Info)
+        .otlp(otlp.clone())
+        .build();
+
+    let (layer, guard) = otlp
+        .init_otlp_exporter(&config, &LogLevelRange::from(&logger))
+        .unwrap();
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        tracing::info_span!("handling").in_scope(|| {
+            tracing::info!(answer = 42, "exported");
+            tracing::debug!("too verbose to export");
+        });
+    });
+
+    drop(guard);
+
+    let requests = requests.lock().unwrap();
+    let find = |path: &str| {
+        requests
+            .iter()
+            .find(|(request_path, ..)| request_path == path)
+            .unwrap_or_else(|| panic!("nothing sent to {path}"))
+    };
+    let contains = |body: &[u8], text: &str| {
+        body.windows(text.len())
+            .any(|window| window == text.as_bytes())
+    };
+
+    let (_, headers, traces) = find("/v1/traces");
+
+    assert!(headers.contains("x-team: ops"), "{headers}");
+    assert!(contains(traces, "handling"));
+    assert!(contains(traces, "otlp-app"));
+    assert!(contains(traces, "production"));
+    assert!(contains(traces, "eu-west"));
+
+    let (_, _, logs) = find("/v1/logs");
+
+    assert!(contains(logs, "exported"));
+    assert!(!contains(logs, "too verbose to export"));
+}
@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+
+use crate::{Configuration, HostDetails, HostSession, LoggingError};
+
+use super::LogFileConfig;
+
+/// Reads what file loggers wrote, here or on a deployed host. Each call to
+/// [`LogReader::read`] picks up where the last one left off, following
+/// files as they rotate.
+pub struct LogReader {
+    files: Vec<LogFileConfig>,
+    source: LogSource,
+    /// Files read so far, by their identity, so renamed files aren't read
+    /// twice.
+    followed: HashMap<String, FollowedFile>,
+    started: bool,
+}
+
+/// How far a file has been read.
+#[derive(Clone, Debug, Default)]
+struct FollowedFile {
+    offset: u64,
+    /// Where it was last seen, to tell which file a compressed one used to
+    /// be.
+    path: PathBuf,
+    /// The last bytes read, to tell when a new file reuses its identity.
+    tail: Vec<u8>,
+}
+
+const TAIL_LENGTH: usize = 32;
+
+/// A log file found in a logger's directory.
+#[derive(Clone, Debug, PartialEq)]
+struct LogFileEntry {
+    path: PathBuf,
+    /// Stays the same when the file is renamed.
+    key: String,
+    size: u64,
+    modified: f64,
+}
+
+impl LogFileEntry {
+    fn compressed(&self) -> bool {
+        self.path
+            .extension()
+            .is_some_and(|extension| extension == "gz")
+    }
+}
+
+enum LogSource {
+    Local,
+    Remote(HostSession),
+}
+
+impl LogReader {
+    /// Read the files of every file logger in the configuration.
+    pub fn new(config: &Configuration) -> Self {
+        Self {
+            files: config
+                .loggers()
+                .into_iter()
+                .filter_map(|logger| logger.file().cloned())
+                .collect(),
+            source: LogSource::Local,
+            followed: HashMap::new(),
+            started: false,
+        }
+    }
+
+    /// Read the same files on a deployed host, with directories relative to
+    /// where its user logs in.
+    pub async fn remote(config: &Configuration, host: HostDetails) -> Result<Self, LoggingError> {
+        Ok(Self {
+            source: LogSource::Remote(HostSession::connect().host(host).call().await?),
+            ..Self::new(config)
+        })
+    }
+
+    /// Every complete line written since the last read, oldest file first.
+    /// Files compressed since the last read pick up where they left off.
+    pub async fn read(&mut self) -> Result<Vec<String>, LoggingError> {
+        let mut lines = Vec::new();
+
+        for file in self.files.clone() {
+            let entries = self.source.list(&file).await?;
+            let (mut unread, mut replaced) = self.check(&file, &entries).await?;
+
+            for entry in &entries {
+                let offset = match (entry.compressed(), self.followed.get(&entry.key)) {
+                    (true, Some(_)) => continue,
+                    (true, None) if !self.started => 0,
+                    (true, None) => {
+                        let uncompressed = entry.path.with_extension("");
+
+                        // Rotated by time, the name stays the same. Rotated
+                        // by size, the file being written was renamed first.
+                        match replaced
+                            .iter()
+                            .position(|followed| followed.path == uncompressed)
+                            .or((!replaced.is_empty()).then_some(0))
+                        {
+                            Some(index) => replaced.remove(index).offset,
+                            // Still being compressed, so wait for the
+                            // original to go.
+                            None if entries.iter().any(|other| other.path == uncompressed) => {
+                                continue
+                            }
+                            None => 0,
+                        }
+                    }
+                    (false, followed) => followed.map_or(0, |followed| followed.offset),
+                };
+
+                let contents = match unread.remove(&entry.key) {
+                    Some(contents) => contents,
+                    None if !entry.compressed() && entry.size == offset => Vec::new(),
+                    None => self.source.read(entry, offset).await?,
+                };
+                let complete = match entry.compressed() {
+                    true => contents.len(),
+                    false => contents
+                        .iter()
+                        .rposition(|byte| *byte == b'\n')
+                        .map_or(0, |position| position + 1),
+                };
+
+                lines.extend(
+                    String::from_utf8_lossy(&contents[..complete])
+                        .lines()
+                        .map(String::from),
+                );
+
+                let followed = self.followed.entry(entry.key.clone()).or_default();
+
+                followed.offset = match entry.compressed() {
+                    true => entry.size,
+                    false => offset + complete as u64,
+                };
+                followed.path = entry.path.clone();
+                followed.tail.extend_from_slice(&contents[..complete]);
+                followed
+                    .tail
+                    .drain(..followed.tail.len().saturating_sub(TAIL_LENGTH));
+            }
+        }
+
+        self.started = true;
+
+        Ok(lines)
+    }
+
+    /// Read what was added to every followed file still there, making sure
+    /// it's the same file by the bytes before its offset. Returns what was
+    /// added, and the followed files that were replaced or went away, by
+    /// where they were last seen.
+    async fn check(
+        &mut self,
+        file: &LogFileConfig,
+        entries: &[LogFileEntry],
+    ) -> Result<(HashMap<String, Vec<u8>>, Vec<FollowedFile>), LoggingError> {
+        let mut unread = HashMap::new();
+        let mut replaced = Vec::new();
+
+        for entry in entries.iter().filter(|entry| !entry.compressed()) {
+            let Some(followed) = self.followed.get(&entry.key) else {
+                continue;
+            };
+
+            if entry.size == followed.offset {
+                continue;
+            }
+
+            // Truncated files start over.
+            let same = match entry.size < followed.offset {
+                true => None,
+                false => {
+                    let start = followed.offset - followed.tail.len() as u64;
+
+                    Some(self.source.read(entry, start).await?)
+                        .filter(|contents| contents.starts_with(&followed.tail))
+                }
+            };
+
+            match same {
+                Some(contents) => {
+                    unread.insert(entry.key.clone(), contents[followed.tail.len()..].to_vec());
+                }
+                None => replaced.extend(self.followed.remove(&entry.key)),
+            }
+        }
+
+        let matcher = file.file_matcher();
+        let gone: Vec<String> = self
+            .followed
+            .iter()
+            .filter(|(key, followed)| {
+                followed.path.parent() == Some(file.directory.as_path())
+                    && followed
+                        .path
+                        .file_name()
+                        .is_some_and(|name| matcher.is_match(&name.to_string_lossy()))
+                    && !entries.iter().any(|entry| entry.key == **key)
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in gone {
+            if let Some(followed) = self.followed.remove(&key) {
+                if followed
+                    .path
+                    .extension()
+                    .is_none_or(|extension| extension != "gz")
+                {
+                    replaced.push(followed);
+                }
+            }
+        }
+
+        replaced.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok((unread, replaced))
+    }
+}
+
+impl LogSource {
+    /// The logger's files, oldest first.
+    async fn list(&self, file: &LogFileConfig) -> Result<Vec<LogFileEntry>, LoggingError> {
+        let matcher = file.file_matcher();
+        let mut entries = match self {
+            Self::Local => list_local(&file.directory)?,
+            Self::Remote(session) => {
+                let listing = session
+                    .output(vec![
+                        "find".to_string(),
+                        file.directory.display().to_string(),
+                        "-maxdepth".to_string(),
+                        "1".to_string(),
+                        "-type".to_string(),
+                        "f".to_string(),
+                        "-printf".to_string(),
+                        r"%T@ %i %s %f\n".to_string(),
+                    ])
+                    .await?;
+
+                parse_listing(&file.directory, &String::from_utf8_lossy(&listing))
+            }
+        };
+
+        entries.retain(|entry| {
+            entry
+                .path
+                .file_name()
+                .is_some_and(|name| matcher.is_match(&name.to_string_lossy()))
+        });
+        entries.sort_by(|a, b| a.modified.total_cmp(&b.modified).then(a.path.cmp(&b.path)));
+
+        Ok(entries)
+    }
+
+    /// The file's contents past the offset, counted in decompressed bytes for
+    /// compressed files.
+    async fn read(&self, entry: &LogFileEntry, offset: u64) -> Result<Vec<u8>, LoggingError> {
+        let unreadable =
+            |error| LoggingError::UnreadableFile(entry.path.display().to_string(), error);
+        let contents = match self {
+            Self::Local => {
+                use std::io::{Seek, SeekFrom};
+
+                let mut file = std::fs::File::open(&entry.path).map_err(unreadable)?;
+                let mut contents = Vec::new();
+
+                match entry.compressed() {
+                    true => GzDecoder::new(file).read_to_end(&mut contents),
+                    false => file
+                        .seek(SeekFrom::Start(offset))
+                        .and_then(|_| file.read_to_end(&mut contents)),
+                }
+                .map_err(unreadable)?;
+
+                contents
+            }
+            Self::Remote(session) => {
+                let path = entry.path.display().to_string();
+                let command = match entry.compressed() {
+                    true => vec!["gzip".to_string(), "-dc".to_string(), path],
+                    false => vec![
+                        "tail".to_string(),
+                        "-c".to_string(),
+                        format!("+{start}", start = offset + 1),
+                        path,
+                    ],
+                };
+
+                session.output(command).await?
+            }
+        };
+
+        Ok(match entry.compressed() {
+            true => contents
+                .get(offset as usize..)
+                .map(<[u8]>::to_vec)
+                .unwrap_or_default(),
+            false => contents,
+        })
+    }
+}
+
+fn list_local(directory: &Path) -> Result<Vec<LogFileEntry>, LoggingError> {
+    let unreadable = |error| LoggingError::UnreadableFile(directory.display().to_string(), error);
+    let mut entries = Vec::new();
+
+    let listing = match std::fs::read_dir(directory) {
+        Ok(listing) => listing,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(entries),
+        Err(error) => return Err(unreadable(error)),
+    };
+
+    for entry in listing {
+        let entry = entry.map_err(unreadable)?;
+        let metadata = entry.metadata().map_err(unreadable)?;
+
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|since| since.as_secs_f64())
+            .unwrap_or_default();
+
+        #[cfg(unix)]
+        let key = {
+            use std::os::unix::fs::MetadataExt;
+
+            format!("{}:{}", metadata.dev(), metadata.ino())
+        };
+        #[cfg(not(unix))]
+        let key = entry.path().display().to_string();
+
+        entries.push(LogFileEntry {
+            path: entry.path(),
+            key,
+            size: metadata.len(),
+            modified,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Read `find -printf '%T@ %i %s %f\n'` output.
+fn parse_listing(directory: &Path, listing: &str) -> Vec<LogFileEntry> {
+    listing
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(4, ' ');
+            let modified = parts.next()?.parse().ok()?;
+            let key = parts.next()?.to_string();
+            let size = parts.next()?.parse().ok()?;
+            let name = parts.next()?;
+
+            Some(LogFileEntry {
+                path: directory.join(name),
+                key,
+                size,
+                modified,
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn reading_rotated_log_files() {
+    use std::io::Write;
+
+    use crate::{LogLevel, LoggerConfig};
+
+    figment::Jail::expect_with(|jail| {
+        let directory = jail.directory().join("logs");
+        let config = Configuration::builder()
+            .logging(bon::vec![LoggerConfig::builder()
+                .level(LogLevel::Info)
+                .file((directory.clone(), "app"))
+                .build()])
+            .build();
+        let append = |name: &str, contents: &str| {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(directory.join(name))
+                .unwrap()
+                .write_all(contents.as_bytes())
+                .unwrap();
+        };
+
+        let compress = |name: &str, contents: &[u8]| {
+            let mut encoder = flate2::write::GzEncoder::new(
+                std::fs::File::create(directory.join(format!("{name}.gz"))).unwrap(),
+                flate2::Compression::default(),
+            );
+            encoder.write_all(contents).unwrap();
+            encoder.finish().unwrap();
+        };
+
+        std::fs::create_dir_all(&directory).unwrap();
+        compress("app.log.1", b"oldest\n");
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        append("app.log", "first\nsecond\npart");
+        append("unrelated.log", "skipped\n");
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut reader = LogReader::new(&config);
+
+        assert_eq!(
+            runtime.block_on(reader.read()).unwrap(),
+            ["oldest", "first", "second"]
+        );
+
+        append("app.log", "ial\n");
+        std::fs::rename(directory.join("app.log"), directory.join("app.log.2")).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        append("app.log", "after rotation\n");
+
+        assert_eq!(
+            runtime.block_on(reader.read()).unwrap(),
+            ["partial", "after rotation"]
+        );
+        assert!(runtime.block_on(reader.read()).unwrap().is_empty());
+
+        // Rotated by size with compression, before the last lines were read.
+        append("app.log", "before compression\n");
+        std::fs::rename(directory.join("app.log"), directory.join("app.log.3")).unwrap();
+        compress(
+            "app.log.3",
+            &std::fs::read(directory.join("app.log.3")).unwrap(),
+        );
+        std::fs::remove_file(directory.join("app.log.3")).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        append("app.log", "after compression\n");
+
+        assert_eq!(
+            runtime.block_on(reader.read()).unwrap(),
+            ["before compression", "after compression"]
+        );
+        assert!(runtime.block_on(reader.read()).unwrap().is_empty());
+
+        // Rotated by time, compressed under the same name.
+        std::fs::rename(directory.join("app.log"), directory.join("app.log.4")).unwrap();
+        assert!(runtime.block_on(reader.read()).unwrap().is_empty());
+
+        let mut rotated = std::fs::read(directory.join("app.log.4")).unwrap();
+        rotated.extend(b"last words\n");
+        compress("app.log.4", &rotated);
+        assert!(runtime.block_on(reader.read()).unwrap().is_empty());
+
+        std::fs::remove_file(directory.join("app.log.4")).unwrap();
+
+        assert_eq!(runtime.block_on(reader.read()).unwrap(), ["last words"]);
+        assert!(runtime.block_on(reader.read()).unwrap().is_empty());
+
+        assert_eq!(
+            parse_listing(
+                Path::new("logs"),
+                "1700000000.5 42 120 app.log\nnot a listing\n"
+            ),
+            [LogFileEntry {
+                path: "logs/app.log".into(),
+                key: "42".into(),
+                size: 120,
+                modified: 1700000000.5,
+            }]
+        );
+
+        Ok(())
+    });
+}
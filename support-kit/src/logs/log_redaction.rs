@@ -0,0 +1,555 @@
+use std::borrow::Cow;
+use std::sync::{Arc, RwLock};
+
+use regex::Regex;
+use schemars::JsonSchema;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use tracing::field::{display, DisplayValue, Field, Value, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata};
+use tracing_subscriber::{layer::Context, Registry};
+
+use crate::{Configuration, TracingTarget, TracingTargets};
+
+const REDACTED: &str = "[redacted]";
+
+/// What to mask in events and spans before any logger writes them. The
+/// `secret` key, the registry token and every value resolved from a secret
+/// reference are always masked.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, bon::Builder)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogRedactionConfig {
+    /// Field names whose values are masked whole, ignoring case.
+    #[serde(default = "default_fields")]
+    #[builder(default = default_fields(), into)]
+    pub fields: Vec<String>,
+    /// Regular expressions masked wherever they match in a value, like
+    /// `Bearer \S+`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[builder(default, into)]
+    pub patterns: Vec<String>,
+}
+
+fn default_fields() -> Vec<String> {
+    ["authorization", "password", "secret", "token"]
+        .map(String::from)
+        .to_vec()
+}
+
+impl Default for LogRedactionConfig {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl LogRedactionConfig {
+    /// The patterns, failing on the first one that isn't a valid regex.
+    pub fn regexes(&self) -> Result<Vec<Regex>, regex::Error> {
+        self.patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect()
+    }
+}
+
+/// Masks secrets in field values. Clones share the same secrets, so ones
+/// resolved after the loggers start are still masked.
+#[derive(Clone, Default)]
+pub struct LogRedactor(Arc<RwLock<Redactions>>);
+
+#[derive(Default)]
+struct Redactions {
+    fields: Vec<String>,
+    patterns: Vec<Regex>,
+    secrets: Vec<SecretString>,
+}
+
+impl LogRedactor {
+    /// Mask what the configuration asks for, along with its own secrets.
+    /// Invalid patterns are skipped with a warning.
+    pub fn new(config: &Configuration) -> Self {
+        let patterns = config
+            .redaction
+            .patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(error) => {
+                    tracing::warn!(%error, pattern, "ignoring invalid redaction pattern");
+                    None
+                }
+            })
+            .collect();
+        let redactor = Self(Arc::new(RwLock::new(Redactions {
+            fields: config
+                .redaction
+                .fields
+                .iter()
+                .map(|field| field.to_lowercase())
+                .collect(),
+            patterns,
+            secrets: Vec::new(),
+        })));
+        let registry_token = config
+            .deployment
+            .as_ref()
+            .and_then(|deployment| deployment.artifacts.as_ref())
+            .and_then(|artifacts| artifacts.containers.as_ref())
+            .and_then(|containers| containers.registry.as_ref())
+            .map(|registry| &registry.token);
+
+        redactor.add_secrets([&config.secret].into_iter().chain(registry_token));
+        redactor
+    }
+
+    /// Mask these values from now on. Empty values are ignored.
+    pub fn add_secrets<'a>(&self, secrets: impl IntoIterator<Item = &'a SecretString>) {
+        let Ok(mut redactions) = self.0.write() else {
+            return;
+        };
+
+        for secret in secrets {
+            let exposed = secret.expose_secret();
+            let known = redactions
+                .secrets
+                .iter()
+                .any(|existing| existing.expose_secret() == exposed);
+
+            if !exposed.is_empty() && !known {
+                redactions.secrets.push(secret.clone());
+            }
+        }
+    }
+
+    /// The value with every secret and pattern masked.
+    pub fn redact<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let Ok(redactions) = self.0.read() else {
+            return Cow::Borrowed(REDACTED);
+        };
+        let mut value = Cow::Borrowed(value);
+
+        for secret in &redactions.secrets {
+            let secret = secret.expose_secret();
+
+            if value.contains(secret) {
+                value = Cow::Owned(value.replace(secret, REDACTED));
+            }
+        }
+
+        for pattern in &redactions.patterns {
+            if let Cow::Owned(replaced) = pattern.replace_all(&value, REDACTED) {
+                value = Cow::Owned(replaced);
+            }
+        }
+
+        value
+    }
+
+    /// Whether values of this field are masked whole.
+    pub fn redacts_field(&self, name: &str) -> bool {
+        self.0
+            .read()
+            .map(|redactions| {
+                redactions
+                    .fields
+                    .iter()
+                    .any(|field| field.eq_ignore_ascii_case(name))
+            })
+            .unwrap_or(true)
+    }
+
+    /// Put the loggers behind this redactor, so none of them see a value it
+    /// would mask.
+    pub fn layer(&self, loggers: TracingTargets) -> TracingTarget {
+        use tracing_subscriber::Layer;
+
+        RedactingLayer {
+            loggers,
+            redactor: self.clone(),
+        }
+        .boxed()
+    }
+
+    /// Every field value with masking applied, or nothing when none of
+    /// them needed it.
+    fn redacted(
+        &self,
+        metadata: &Metadata<'_>,
+        record: impl FnOnce(&mut dyn Visit),
+    ) -> Option<Vec<Option<RedactedValue>>> {
+        let mut visitor = RedactingVisitor {
+            redactor: self,
+            values: (0..metadata.fields().len()).map(|_| None).collect(),
+            changed: false,
+        };
+
+        record(&mut visitor);
+
+        visitor.changed.then_some(visitor.values)
+    }
+}
+
+impl std::fmt::Debug for LogRedactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redactions = self.0.read();
+        let mut debug = f.debug_struct("LogRedactor");
+
+        if let Ok(redactions) = redactions {
+            debug
+                .field("fields", &redactions.fields)
+                .field("patterns", &redactions.patterns.len())
+                .field("secrets", &redactions.secrets.len());
+        }
+
+        debug.finish()
+    }
+}
+
+/// A field value as it was recorded, or with secrets masked.
+enum RedactedValue {
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    I128(i128),
+    U128(u128),
+    F64(f64),
+    Str(String),
+    Debug(DisplayValue<String>),
+}
+
+impl RedactedValue {
+    fn as_value(&self) -> &dyn Value {
+        match self {
+            Self::Bool(value) => value,
+            Self::I64(value) => value,
+            Self::U64(value) => value,
+            Self::I128(value) => value,
+            Self::U128(value) => value,
+            Self::F64(value) => value,
+            Self::Str(value) => value,
+            Self::Debug(value) => value,
+        }
+    }
+}
+
+fn as_values(values: &[Option<RedactedValue>]) -> Vec<Option<&dyn Value>> {
+    values
+        .iter()
+        .map(|value| value.as_ref().map(RedactedValue::as_value))
+        .collect()
+}
+
+/// Records every field of an event or span by position in its metadata,
+/// masking values along the way.
+struct RedactingVisitor<'a> {
+    redactor: &'a LogRedactor,
+    values: Vec<Option<RedactedValue>>,
+    changed: bool,
+}
+
+impl RedactingVisitor<'_> {
+    fn set(&mut self, field: &Field, value: RedactedValue) {
+        if let Some(slot) = self.values.get_mut(field.index()) {
+            *slot = Some(value);
+        }
+    }
+
+    fn set_text(&mut self, field: &Field, text: &str, debug: bool) {
+        let redacted = match self.redactor.redacts_field(field.name()) {
+            true => Cow::Borrowed(REDACTED),
+            false => self.redactor.redact(text),
+        };
+
+        self.changed |= redacted != text;
+
+        let value = match debug {
+            true => RedactedValue::Debug(display(redacted.into_owned())),
+            false => RedactedValue::Str(redacted.into_owned()),
+        };
+
+        self.set(field, value);
+    }
+
+    fn set_number(&mut self, field: &Field, value: RedactedValue) {
+        match self.redactor.redacts_field(field.name()) {
+            true => {
+                self.changed = true;
+                self.set(field, RedactedValue::Str(REDACTED.to_string()));
+            }
+            false => self.set(field, value),
+        }
+    }
+}
+
+impl Visit for RedactingVisitor<'_> {
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set_number(field, RedactedValue::Bool(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set_number(field, RedactedValue::I64(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set_number(field, RedactedValue::U64(value));
+    }
+
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.set_number(field, RedactedValue::I128(value));
+    }
+
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        self.set_number(field, RedactedValue::U128(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set_number(field, RedactedValue::F64(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set_text(field, value, false);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.set_text(field, &format!("{value:?}"), true);
+    }
+}
+
+/// Hands every logger events and spans with their values masked. Events
+/// and spans with nothing to mask are passed along as they are.
+struct RedactingLayer {
+    loggers: TracingTargets,
+    redactor: LogRedactor,
+}
+
+impl tracing_subscriber::Layer<Registry> for RedactingLayer {
+    fn on_register_dispatch(&self, subscriber: &tracing::Dispatch) {
+        self.loggers.on_register_dispatch(subscriber);
+    }
+
+    fn on_layer(&mut self, subscriber: &mut Registry) {
+        self.loggers.on_layer(subscriber);
+    }
+
+    fn register_callsite(
+        &self,
+        metadata: &'static Metadata<'static>,
+    ) -> tracing::subscriber::Interest {
+        self.loggers.register_callsite(metadata)
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, context: Context<'_, Registry>) -> bool {
+        self.loggers.enabled(metadata, context)
+    }
+
+    fn max_level_hint(&self) -> Option<tracing::level_filters::LevelFilter> {
+        self.loggers.max_level_hint()
+    }
+
+    fn event_enabled(&self, event: &Event<'_>, context: Context<'_, Registry>) -> bool {
+        self.loggers.event_enabled(event, context)
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, context: Context<'_, Registry>) {
+        let metadata = attrs.metadata();
+        let Some(values) = self
+            .redactor
+            .redacted(metadata, |visitor| attrs.record(visitor))
+        else {
+            return self.loggers.on_new_span(attrs, id, context);
+        };
+        let values = as_values(&values);
+        let values = metadata.fields().value_set_all(&values);
+        let redacted = match (attrs.is_contextual(), attrs.parent()) {
+            (true, _) => Attributes::new(metadata, &values),
+            (false, Some(parent)) => Attributes::child_of(parent.clone(), metadata, &values),
+            (false, None) => Attributes::new_root(metadata, &values),
+        };
+
+        self.loggers.on_new_span(&redacted, id, context);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, context: Context<'_, Registry>) {
+        let Some(metadata) = context.metadata(id) else {
+            return self.loggers.on_record(id, values, context);
+        };
+        let Some(redacted) = self
+            .redactor
+            .redacted(metadata, |visitor| values.record(visitor))
+        else {
+            return self.loggers.on_record(id, values, context);
+        };
+        let redacted = as_values(&redacted);
+        let redacted = metadata.fields().value_set_all(&redacted);
+
+        self.loggers.on_record(id, &Record::new(&redacted), context);
+    }
+
+    fn on_follows_from(&self, span: &Id, follows: &Id, context: Context<'_, Registry>) {
+        self.loggers.on_follows_from(span, follows, context);
+    }
+
+    fn on_event(&self, event: &Event<'_>, context: Context<'_, Registry>) {
+        let metadata = event.metadata();
+        let Some(values) = self
+            .redactor
+            .redacted(metadata, |visitor| event.record(visitor))
+        else {
+            return self.loggers.on_event(event, context);
+        };
+        let values = as_values(&values);
+        let values = metadata.fields().value_set_all(&values);
+        let redacted = match event.is_contextual() {
+            true => Event::new(metadata, &values),
+            false => Event::new_child_of(event.parent().cloned(), metadata, &values),
+        };
+
+        self.loggers.on_event(&redacted, context);
+    }
+
+    fn on_enter(&self, id: &Id, context: Context<'_, Registry>) {
+        self.loggers.on_enter(id, context);
+    }
+
+    fn on_exit(&self, id: &Id, context: Context<'_, Registry>) {
+        self.loggers.on_exit(id, context);
+    }
+
+    fn on_close(&self, id: Id, context: Context<'_, Registry>) {
+        self.loggers.on_close(id, context);
+    }
+
+    fn on_id_change(&self, old: &Id, new: &Id, context: Context<'_, Registry>) {
+        self.loggers.on_id_change(old, new, context);
+    }
+
+    unsafe fn downcast_raw(&self, id: std::any::TypeId) -> Option<*const ()> {
+        if id == std::any::TypeId::of::<Self>() {
+            return Some(self as *const Self as *const ());
+        }
+
+        // SAFETY: forwarded as is, so the loggers' own guarantees hold.
+        unsafe { self.loggers.downcast_raw(id) }
+    }
+}
+
+#[test]
+fn redacting_secrets_from_every_logger() {
+    use std::io::Write;
+    use std::sync::Mutex;
+    use tracing_subscriber::{layer::SubscriberExt, Layer};
+
+    use super::log_otlp_config::test_collector;
+    use crate::{LogLevel, LogMemoryConfig, LogOtlpConfig, LogOtlpProtocol, LoggerConfig, Logging};
+
+    /// Stands in for stdout, which tests can't read back.
+    #[derive(Clone, Default)]
+    struct Console(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Console {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let (address, requests) = test_collector();
+
+    figment::Jail::expect_with(|jail| {
+        let directory = jail.directory().join("logs");
+        let logger = || LoggerConfig::builder().level(LogLevel::Error..LogLevel::Trace);
+        let config = Configuration::builder()
+            .secret(SecretString::from("hunter2"))
+            .redaction(
+                LogRedactionConfig::builder()
+                    .patterns([r"Bearer \S+".to_string()])
+                    .build(),
+            )
+            .logging(bon::vec![
+                logger().file((directory.clone(), "app")).build(),
+                logger()
+                    .otlp(
+                        LogOtlpConfig::builder()
+                            .endpoint(format!("http://{address}"))
+                            .protocol(LogOtlpProtocol::HttpProtobuf)
+                            .build(),
+                    )
+                    .build(),
+                logger().memory(LogMemoryConfig::default()).build(),
+            ])
+            .build();
+
+        assert_ne!(
+            config,
+            Configuration {
+                redaction: LogRedactionConfig::default(),
+                ..config.clone()
+            }
+        );
+
+        let Logging {
+            mut loggers,
+            guards,
+            handle,
+            ..
+        } = Logging::new(&config).unwrap();
+        let console = Console::default();
+        let writer = console.clone();
+
+        handle
+            .redactor
+            .add_secrets([&SecretString::from("ghcr-token")]);
+        loggers.push(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || writer.clone())
+                .boxed(),
+        );
+
+        let subscriber = tracing_subscriber::registry().with(handle.redactor.layer(loggers));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("login", token = "ghcr-token").in_scope(|| {
+                tracing::info!(command = "docker login -u me -p ghcr-token", "logging in");
+                tracing::info!(password = 987654, user = "ada", "checking password");
+                tracing::warn!(header = "Bearer abc.def", "hunter2 ended up in a message");
+            });
+        });
+
+        drop(guards);
+
+        let file = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect::<String>();
+        let console = String::from_utf8(console.0.lock().unwrap().clone()).unwrap();
+        let memory = format!("{:?}", handle.buffers[0].events());
+        let otlp = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, _, body)| String::from_utf8_lossy(body).into_owned())
+            .collect::<String>();
+
+        for (sink, output) in [
+            ("file", file),
+            ("console", console),
+            ("memory", memory),
+            ("otlp", otlp),
+        ] {
+            assert!(output.contains("logging in"), "{sink}: {output}");
+            assert!(output.contains("ada"), "{sink}: {output}");
+            assert!(output.contains(REDACTED), "{sink}: {output}");
+
+            for secret in ["ghcr-token", "hunter2", "abc.def", "987654"] {
+                assert!(!output.contains(secret), "{sink} leaked {secret}: {output}");
+            }
+        }
+
+        Ok(())
+    });
+}
@@ -1,6 +1,8 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub enum LogRotation {
     Daily,
@@ -9,3 +11,18 @@ pub enum LogRotation {
     #[default]
     Never,
 }
+
+impl LogRotation {
+    /// The `{date}` in log file names for the period containing `now`, in
+    /// UTC. Empty when files never rotate by time.
+    pub fn period(&self, now: DateTime<Utc>) -> String {
+        let format = match self {
+            Self::Daily => "%Y-%m-%d",
+            Self::Hourly => "%Y-%m-%d-%H",
+            Self::PerMinute => "%Y-%m-%d-%H-%M",
+            Self::Never => return String::new(),
+        };
+
+        now.format(format).to_string()
+    }
+}
@@ -0,0 +1,97 @@
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, fmt::Display, str::FromStr};
+
+/// A file size in bytes, written as a number of bytes or with a `KB`, `MB`
+/// or `GB` suffix. Suffixes are powers of 1024.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(try_from = "SizeOrString", into = "String")]
+pub struct LogSize(pub u64);
+
+const UNITS: [(&str, u64); 4] = [
+    ("GB", 1024 * 1024 * 1024),
+    ("MB", 1024 * 1024),
+    ("KB", 1024),
+    ("B", 1),
+];
+
+impl LogSize {
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FromStr for LogSize {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let upper = input.trim().to_ascii_uppercase();
+        let (number, multiplier) = UNITS
+            .iter()
+            .find_map(|(unit, multiplier)| Some((upper.strip_suffix(unit)?, *multiplier)))
+            .unwrap_or((&upper, 1));
+
+        number
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| number.checked_mul(multiplier))
+            .map(Self)
+            .ok_or_else(|| format!("invalid size `{input}`, expected e.g. `10MB`"))
+    }
+}
+
+impl Display for LogSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (unit, multiplier) = UNITS
+            .iter()
+            .find(|(_, multiplier)| self.0 >= *multiplier && self.0.is_multiple_of(*multiplier))
+            .unwrap_or(&("B", 1));
+
+        write!(f, "{}{unit}", self.0 / multiplier)
+    }
+}
+
+impl From<LogSize> for String {
+    fn from(size: LogSize) -> Self {
+        size.to_string()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SizeOrString {
+    Size(u64),
+    String(String),
+}
+
+impl TryFrom<SizeOrString> for LogSize {
+    type Error = String;
+
+    fn try_from(value: SizeOrString) -> Result<Self, Self::Error> {
+        let size = match value {
+            SizeOrString::Size(bytes) => Self(bytes),
+            SizeOrString::String(size) => size.parse()?,
+        };
+
+        // A file can't hold any writes before it's full.
+        match size.bytes() {
+            0 => Err("a size of 0 would rotate after every write".to_string()),
+            _ => Ok(size),
+        }
+    }
+}
+
+impl JsonSchema for LogSize {
+    fn schema_name() -> Cow<'static, str> {
+        "LogSize".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": ["integer", "string"],
+            "minimum": 1,
+            "pattern": "^[0-9]+\\s*([KMG]?B)?$"
+        })
+    }
+}
@@ -0,0 +1,359 @@
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::net::{TcpStream, UdpSocket};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+
+use crate::{Configuration, LoggingError, TracingTarget};
+
+use super::LogLevelRange;
+
+/// The structured data element each event's fields go in. 32473 is the
+/// enterprise number set aside for examples and private use.
+const FIELDS_ID: &str = "fields@32473";
+
+/// Where syslog messages are sent.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogSyslogTransport {
+    /// A local datagram socket, like `/dev/log`.
+    Unix(PathBuf),
+    /// A `host:port` address, one message per datagram.
+    Udp(String),
+    /// A `host:port` address, with each message prefixed by its length.
+    Tcp(String),
+}
+
+impl Default for LogSyslogTransport {
+    fn default() -> Self {
+        Self::Unix("/dev/log".into())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogSyslogFacility {
+    User,
+    #[default]
+    Daemon,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl LogSyslogFacility {
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::User => 1,
+            Self::Daemon => 3,
+            Self::Local0 => 16,
+            Self::Local1 => 17,
+            Self::Local2 => 18,
+            Self::Local3 => 19,
+            Self::Local4 => 20,
+            Self::Local5 => 21,
+            Self::Local6 => 22,
+            Self::Local7 => 23,
+        }
+    }
+}
+
+/// Sends events to syslog as RFC 5424 messages, with each event's fields as
+/// structured data.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, bon::Builder)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogSyslogConfig {
+    #[serde(default)]
+    #[builder(default)]
+    pub transport: LogSyslogTransport,
+    #[serde(default)]
+    #[builder(default)]
+    pub facility: LogSyslogFacility,
+    /// The `APP-NAME` of each message. Defaults to the service name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub app_name: Option<String>,
+}
+
+impl LogSyslogConfig {
+    /// Build a logger sending to syslog, failing if the transport can't be
+    /// set up.
+    pub fn init_syslog_logger(
+        &self,
+        config: &Configuration,
+        levels: &LogLevelRange,
+    ) -> Result<TracingTarget, LoggingError> {
+        use tracing_subscriber::Layer;
+
+        let app_name = match &self.app_name {
+            Some(app_name) => app_name.clone(),
+            None => config.name().to_string(),
+        };
+
+        Ok(SyslogLayer {
+            socket: Mutex::new(SyslogSocket::connect(&self.transport)?),
+            transport: self.transport.clone(),
+            facility: self.facility,
+            header: format!(
+                "{hostname} {app_name} {pid} -",
+                hostname = header_field(&gethostname::gethostname().to_string_lossy(), 255),
+                app_name = header_field(&app_name, 48),
+                pid = std::process::id()
+            ),
+            levels: levels.clone(),
+        }
+        .boxed())
+    }
+}
+
+enum SyslogSocket {
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixDatagram),
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+impl SyslogSocket {
+    fn connect(transport: &LogSyslogTransport) -> std::io::Result<Self> {
+        match transport {
+            #[cfg(unix)]
+            LogSyslogTransport::Unix(path) => {
+                let socket = std::os::unix::net::UnixDatagram::unbound()?;
+                socket.connect(path)?;
+                Ok(Self::Unix(socket))
+            }
+            #[cfg(not(unix))]
+            LogSyslogTransport::Unix(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "unix sockets aren't supported here",
+            )),
+            LogSyslogTransport::Udp(address) => {
+                let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+                socket.connect(address)?;
+                Ok(Self::Udp(socket))
+            }
+            LogSyslogTransport::Tcp(address) => Ok(Self::Tcp(TcpStream::connect(address)?)),
+        }
+    }
+
+    fn send(&mut self, message: &str) -> std::io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Self::Udp(socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Self::Tcp(stream) => {
+                stream.write_all(format!("{len} {message}", len = message.len()).as_bytes())
+            }
+        }
+    }
+}
+
+struct SyslogLayer {
+    socket: Mutex<SyslogSocket>,
+    transport: LogSyslogTransport,
+    facility: LogSyslogFacility,
+    /// The hostname, app name, process ID and message ID of every message.
+    header: String,
+    levels: LogLevelRange,
+}
+
+impl SyslogLayer {
+    fn message(&self, event: &Event<'_>) -> String {
+        let mut fields = SyslogFields::default();
+        event.record(&mut fields);
+
+        let severity = match *event.metadata().level() {
+            Level::ERROR => 3,
+            Level::WARN => 4,
+            Level::INFO => 6,
+            _ => 7,
+        };
+        let mut message = format!(
+            "<{priority}>1 {timestamp} {header} [{FIELDS_ID} target=\"{target}\"",
+            priority = self.facility.code() * 8 + severity,
+            timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+            header = self.header,
+            target = param_value(event.metadata().target()),
+        );
+
+        for (name, value) in &fields.params {
+            let _ = write!(message, " {name}=\"{value}\"");
+        }
+
+        message.push(']');
+
+        if let Some(text) = fields.message {
+            message.push(' ');
+            message.push_str(&text);
+        }
+
+        message
+    }
+}
+
+impl<S: Subscriber> tracing_subscriber::Layer<S> for SyslogLayer {
+    fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+        if !self.levels.contains(event.metadata().level()) {
+            return;
+        }
+
+        let message = self.message(event);
+        let Ok(mut socket) = self.socket.lock() else {
+            return;
+        };
+
+        // Reconnect once, in case the server restarted since.
+        if socket.send(&message).is_err() {
+            if let Ok(reconnected) = SyslogSocket::connect(&self.transport) {
+                *socket = reconnected;
+                let _ = socket.send(&message);
+            }
+        }
+    }
+}
+
+/// An event's `message`, and every other field as a structured data param.
+#[derive(Default)]
+struct SyslogFields {
+    message: Option<String>,
+    params: Vec<(String, String)>,
+}
+
+impl Visit for SyslogFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = Some(value.to_string()),
+            name => self.params.push((param_name(name), param_value(value))),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{value:?}"));
+    }
+}
+
+/// A header field: printable ASCII without spaces, `-` when empty.
+fn header_field(value: &str, max: usize) -> String {
+    let value: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(max)
+        .collect();
+
+    match value.is_empty() {
+        true => "-".to_string(),
+        false => value,
+    }
+}
+
+/// A structured data param name: up to 32 printable ASCII characters, none
+/// of them `=`, `]`, `"` or spaces.
+fn param_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '=' | ']' | '"' => '_',
+            c if c.is_ascii_graphic() => c,
+            _ => '_',
+        })
+        .take(32)
+        .collect()
+}
+
+/// A structured data param value, with `"`, `\` and `]` escaped.
+fn param_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[test]
+fn sending_to_syslog() {
+    use std::io::{BufRead, BufReader, Read};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::{LogLevel, LoggerConfig, ServiceConfig};
+
+    let config = Configuration::builder()
+        .service(ServiceConfig::builder().name("syslog-app").build())
+        .build();
+    let levels = LogLevelRange::from(&LoggerConfig::builder().level(LogLevel::Info).build());
+    let emit = |syslog: LogSyslogConfig| {
+        let logger = syslog.init_syslog_logger(&config, &levels).unwrap();
+
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(logger), || {
+            tracing::debug!("too verbose to send");
+            tracing::info!(answer = 42, quote = "say \"hi\" [ok]", "sent");
+        });
+    };
+
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    emit(
+        LogSyslogConfig::builder()
+            .transport(LogSyslogTransport::Udp(
+                server.local_addr().unwrap().to_string(),
+            ))
+            .facility(LogSyslogFacility::Local0)
+            .build(),
+    );
+
+    let mut datagram = [0; 1024];
+    let length = server.recv(&mut datagram).unwrap();
+    let message = std::str::from_utf8(&datagram[..length]).unwrap();
+    let (header, rest) = message.split_once(" [").unwrap();
+    let header: Vec<&str> = header.split(' ').collect();
+
+    assert_eq!(header[0], "<134>1");
+    assert_eq!(header[3], "syslog-app");
+    assert_eq!(header[4], std::process::id().to_string());
+    assert_eq!(header[5], "-");
+    assert!(
+        rest.ends_with(r#" answer="42" quote="say \"hi\" [ok\]"] sent"#),
+        "{message}"
+    );
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    emit(
+        LogSyslogConfig::builder()
+            .transport(LogSyslogTransport::Tcp(
+                listener.local_addr().unwrap().to_string(),
+            ))
+            .app_name("tcp app")
+            .build(),
+    );
+
+    let mut reader = BufReader::new(listener.accept().unwrap().0);
+    let mut length = Vec::new();
+    reader.read_until(b' ', &mut length).unwrap();
+
+    let length: usize = std::str::from_utf8(&length)
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    let mut message = vec![0; length];
+    reader.read_exact(&mut message).unwrap();
+    let message = String::from_utf8(message).unwrap();
+
+    assert!(message.starts_with("<30>1 "), "{message}");
+    assert!(message.contains(" tcpapp "), "{message}");
+    assert!(message.ends_with("] sent"), "{message}");
+}
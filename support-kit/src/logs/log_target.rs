@@ -1,41 +1,49 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use tracing_subscriber::reload;
 
 use crate::{Configuration, TracingTarget};
 
-use super::LoggerConfig;
+use super::{
+    LogFormat, LogFormatConfig, LogLevelRange, LogTimestamp, LoggerConfig, VerbosityFilterHandle,
+};
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub enum LogTarget {
     Stdout,
     Stderr,
 }
 
 impl LogTarget {
+    /// Build a console logger along with a handle for swapping its verbosity
+    /// filter while running.
     pub fn init_console_logger(
         &self,
         config: &Configuration,
-        logger_config: &LoggerConfig,
-    ) -> TracingTarget {
-        use tracing_subscriber::fmt::writer::MakeWriterExt;
+        logger: &LoggerConfig,
+        levels: &LogLevelRange,
+    ) -> (TracingTarget, VerbosityFilterHandle) {
+        use std::io::IsTerminal;
         use tracing_subscriber::Layer;
 
-        match self {
-            LogTarget::Stderr => tracing_subscriber::fmt::layer()
-                .with_writer(
-                    std::io::stderr
-                        .with_max_level(logger_config.max_tracing_level())
-                        .with_min_level(logger_config.min_tracing_level()),
-                )
-                .with_filter(config.env_filter())
-                .boxed(),
-            LogTarget::Stdout => tracing_subscriber::fmt::layer()
-                .with_writer(
-                    std::io::stdout
-                        .with_max_level(logger_config.max_tracing_level())
-                        .with_min_level(logger_config.min_tracing_level()),
-                )
-                .with_filter(config.env_filter())
-                .boxed(),
-        }
+        let (filter, handle) = reload::Layer::new(logger.env_filter(config, true));
+        let output = logger.output();
+        let defaults = |terminal: bool| {
+            LogFormatConfig::builder()
+                .format(LogFormat::Full)
+                .timestamp(LogTimestamp::Utc)
+                .ansi(config.color.enabled(terminal))
+                .build()
+        };
+        let logger = match self {
+            LogTarget::Stderr => output
+                .or(defaults(std::io::stderr().is_terminal()))
+                .layer(levels.writer(std::io::stderr)),
+            LogTarget::Stdout => output
+                .or(defaults(std::io::stdout().is_terminal()))
+                .layer(levels.writer(std::io::stdout)),
+        };
+
+        (logger.with_filter(filter).boxed(), handle)
     }
 }
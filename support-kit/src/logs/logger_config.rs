@@ -1,18 +1,43 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use tracing_subscriber::{
+    filter::{Directive, ParseError},
+    reload, EnvFilter,
+};
 
-use crate::Configuration;
+use crate::{Configuration, LoggingError, TracingTarget};
 
-use super::{LogFileConfig, LogLevel, LogLevelConfig, LogTarget, LoggerConfigOrPreset, Logging};
+use super::{
+    LogFileConfig, LogFormatConfig, LogJournaldConfig, LogLevel, LogLevelConfig, LogLevelRange,
+    LogMemoryConfig, LogOtlpConfig, LogSyslogConfig, LogTarget, LoggerConfigOrPreset,
+    LoggerFilterHandle, Logging,
+};
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, bon::Builder)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, bon::Builder)]
 #[serde(rename_all = "kebab-case")]
 pub struct LoggerConfig {
     console: Option<LogTarget>,
     #[serde(flatten)]
     #[builder(into)]
     file: Option<LogFileConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    otlp: Option<LogOtlpConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    journald: Option<LogJournaldConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    syslog: Option<LogSyslogConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    memory: Option<LogMemoryConfig>,
     #[builder(into)]
     level: LogLevelConfig,
+    /// `EnvFilter` directives for this logger alone, like `hyper=warn` or
+    /// `off,my_app::audit=info`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    filter: Option<String>,
+    #[serde(flatten)]
+    #[builder(default)]
+    output: LogFormatConfig,
 }
 
 impl LoggerConfig {
@@ -28,33 +53,146 @@ impl LoggerConfig {
         self.level.max_level().tracing_level()
     }
 
+    pub fn output(&self) -> &LogFormatConfig {
+        &self.output
+    }
+
+    pub fn file(&self) -> Option<&LogFileConfig> {
+        self.file.as_ref()
+    }
+
+    pub fn otlp(&self) -> Option<&LogOtlpConfig> {
+        self.otlp.as_ref()
+    }
+
+    /// The directives in this logger's `filter`.
+    pub fn directives(&self) -> Result<Vec<Directive>, ParseError> {
+        self.filter
+            .as_deref()
+            .map(parse_directives)
+            .unwrap_or(Ok(Vec::new()))
+    }
+
+    /// The filter for this logger. From lowest to highest precedence, it
+    /// starts from the verbosity for the console, or every event for other
+    /// destinations, then `RUST_LOG` replaces that when it's set, then this
+    /// logger's `filter`. Events still have to fall in the level range to be
+    /// written.
+    pub fn env_filter(&self, config: &Configuration, console: bool) -> EnvFilter {
+        let base = match console {
+            true => config.env_filter(),
+            false => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("trace")),
+        };
+        let directives = self.directives().unwrap_or_else(|error| {
+            tracing::warn!(%error, filter = ?self.filter, "ignoring invalid logger filter");
+            Vec::new()
+        });
+
+        directives
+            .into_iter()
+            .fold(base, |filter, directive| filter.add_directive(directive))
+    }
+
     pub fn with_console_target(mut self, console: LogTarget) -> Self {
         self.console = Some(console);
         self
     }
 
-    pub fn initialize(&self, config: &Configuration, logging: &mut Logging) {
+    pub fn initialize(
+        &self,
+        config: &Configuration,
+        logging: &mut Logging,
+    ) -> Result<(), LoggingError> {
+        let levels = LogLevelRange::from(self);
+        let position = logging.handle.levels.len();
+
         match &self.file {
             Some(file_config) => {
-                let (logger, guard) = file_config.init_log_appender(&self);
+                let (logger, guard) = file_config.init_log_appender(&levels, &self.output)?;
 
-                logging.loggers.push(logger);
-                logging.guards.push(guard);
+                self.push_filtered(config, logging, position, logger);
+                logging.guards.push(guard.into());
             }
             _ => {}
         }
 
+        if let Some(otlp_config) = &self.otlp {
+            let (logger, guard) = otlp_config.init_otlp_exporter(config, &levels)?;
+
+            self.push_filtered(config, logging, position, logger);
+            logging.guards.push(guard.into());
+        }
+
+        if let Some(journald_config) = &self.journald {
+            let logger = journald_config.init_journald_logger(config, &levels)?;
+
+            self.push_filtered(config, logging, position, logger);
+        }
+
+        if let Some(syslog_config) = &self.syslog {
+            let logger = syslog_config.init_syslog_logger(config, &levels)?;
+
+            self.push_filtered(config, logging, position, logger);
+        }
+
+        if let Some(memory_config) = &self.memory {
+            let buffer = memory_config.init_log_buffer();
+
+            self.push_filtered(config, logging, position, buffer.layer(&levels));
+            logging.handle.buffers.push(buffer);
+        }
+
         match &self.console {
             Some(console_target) => {
-                let logger = console_target.init_console_logger(config, &self);
+                let (logger, handle) = console_target.init_console_logger(config, self, &levels);
 
                 logging.loggers.push(logger);
+                logging.handle.filters.push(LoggerFilterHandle {
+                    logger: position,
+                    console: true,
+                    handle,
+                });
             }
             _ => {}
         }
+
+        logging.handle.levels.push(levels);
+
+        Ok(())
+    }
+
+    /// Add a logger other than the console, behind a filter that can be
+    /// swapped while running.
+    fn push_filtered(
+        &self,
+        config: &Configuration,
+        logging: &mut Logging,
+        position: usize,
+        logger: TracingTarget,
+    ) {
+        use tracing_subscriber::Layer;
+
+        let (filter, handle) = reload::Layer::new(self.env_filter(config, false));
+
+        logging.loggers.push(logger.with_filter(filter).boxed());
+        logging.handle.filters.push(LoggerFilterHandle {
+            logger: position,
+            console: false,
+            handle,
+        });
     }
 }
 
+/// The comma separated directives in a filter, like `hyper=warn,my_app=trace`.
+pub(crate) fn parse_directives(filter: &str) -> Result<Vec<Directive>, ParseError> {
+    filter
+        .split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
 impl From<LoggerConfig> for LoggerConfigOrPreset {
     fn from(logger_config: LoggerConfig) -> Self {
         Self::Config(logger_config)
@@ -233,3 +371,70 @@ fn constructing_loggers_from_logger_config() -> Result<(), Box<dyn std::error::E
 
     Ok(())
 }
+
+#[test]
+fn per_logger_filters() {
+    use clap::Parser;
+
+    use crate::{Args, SupportControl, Verbosity};
+
+    let logger = LoggerConfig::builder()
+        .level(LogLevel::Info)
+        .filter("hyper=warn, my_app::audit=info")
+        .build();
+    let config = Configuration::builder().verbosity(Verbosity::Debug).build();
+
+    assert_eq!(logger.directives().unwrap().len(), 2);
+
+    let file_filter = logger.env_filter(&config, false).to_string();
+
+    assert!(file_filter.contains("hyper=warn"), "{file_filter}");
+    assert!(file_filter.contains("my_app::audit=info"), "{file_filter}");
+    assert!(file_filter.contains("trace"), "{file_filter}");
+
+    let silenced = LoggerConfig::builder()
+        .level(LogLevel::Info)
+        .filter("off,my_app=trace")
+        .build()
+        .env_filter(&config, false)
+        .to_string();
+
+    assert_eq!(silenced, "my_app=trace,off");
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file(
+            "support-kit.yaml",
+            r#"
+            strict: true
+            logging:
+              - console: Stdout
+                level: info
+                filter: hyper=loud
+        "#,
+        )?;
+
+        let args = Args::try_parse_from("app".split_whitespace()).unwrap();
+        let error = SupportControl::load_configuration(&args).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("`logging.0.filter` is not a valid filter"));
+
+        jail.set_env("RUST_LOG", "my_app=debug,hyper=debug");
+
+        let file_filter = LoggerConfig::builder()
+            .level(LogLevel::Info)
+            .file(("logs", "app"))
+            .filter("hyper=warn")
+            .build()
+            .env_filter(&config, false)
+            .to_string();
+
+        assert!(file_filter.contains("my_app=debug"), "{file_filter}");
+        assert!(file_filter.contains("hyper=warn"), "{file_filter}");
+        assert!(!file_filter.contains("hyper=debug"), "{file_filter}");
+        assert!(!file_filter.contains("trace"), "{file_filter}");
+
+        Ok(())
+    });
+}
@@ -1,8 +1,17 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use super::{LogLevel, LogRotation, LogTarget, LoggerConfig, LoggerConfigOrPreset};
+use super::{
+    LogFormat, LogFormatConfig, LogJournaldConfig, LogLevel, LogMemoryConfig, LogRotation,
+    LogSyslogConfig, LogTarget, LogTimestamp, LoggerConfig, LoggerConfigOrPreset,
+};
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/// Common loggers. `json-stdout` writes JSON to stdout for containers,
+/// `logfmt-stdout` does the same in logfmt, and `pretty-stderr` writes every
+/// level to stderr in a readable layout for development. `journald` and
+/// `syslog` send info and above to the system log, for services. `memory`
+/// keeps the latest events of every level to query while running.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub enum LoggerPreset {
     Error,
@@ -10,6 +19,12 @@ pub enum LoggerPreset {
     RollingDebug,
     Stdout,
     Stderr,
+    JsonStdout,
+    LogfmtStdout,
+    PrettyStderr,
+    Journald,
+    Syslog,
+    Memory,
 }
 
 impl From<LoggerPreset> for LoggerConfig {
@@ -20,6 +35,12 @@ impl From<LoggerPreset> for LoggerConfig {
             LoggerPreset::RollingDebug => rolling_debug(),
             LoggerPreset::Stdout => stdout(),
             LoggerPreset::Stderr => stderr(),
+            LoggerPreset::JsonStdout => json_stdout(),
+            LoggerPreset::LogfmtStdout => logfmt_stdout(),
+            LoggerPreset::PrettyStderr => pretty_stderr(),
+            LoggerPreset::Journald => journald(),
+            LoggerPreset::Syslog => syslog(),
+            LoggerPreset::Memory => memory(),
         }
     }
 }
@@ -65,6 +86,63 @@ fn stderr() -> LoggerConfig {
         .build()
 }
 
+fn json_stdout() -> LoggerConfig {
+    LoggerConfig::builder()
+        .level(LogLevel::Info..LogLevel::Trace)
+        .console(LogTarget::Stdout)
+        .output(
+            LogFormatConfig::builder()
+                .format(LogFormat::Json)
+                .timestamp(LogTimestamp::Utc)
+                .ansi(false)
+                .build(),
+        )
+        .build()
+}
+
+fn logfmt_stdout() -> LoggerConfig {
+    LoggerConfig::builder()
+        .level(LogLevel::Info..LogLevel::Trace)
+        .console(LogTarget::Stdout)
+        .output(LogFormatConfig::builder().format(LogFormat::Logfmt).build())
+        .build()
+}
+
+fn pretty_stderr() -> LoggerConfig {
+    LoggerConfig::builder()
+        .level(LogLevel::Error..LogLevel::Trace)
+        .console(LogTarget::Stderr)
+        .output(
+            LogFormatConfig::builder()
+                .format(LogFormat::Pretty)
+                .timestamp(LogTimestamp::Uptime)
+                .file_line(true)
+                .build(),
+        )
+        .build()
+}
+
+fn journald() -> LoggerConfig {
+    LoggerConfig::builder()
+        .level(LogLevel::Error..LogLevel::Info)
+        .journald(LogJournaldConfig::default())
+        .build()
+}
+
+fn syslog() -> LoggerConfig {
+    LoggerConfig::builder()
+        .level(LogLevel::Error..LogLevel::Info)
+        .syslog(LogSyslogConfig::default())
+        .build()
+}
+
+fn memory() -> LoggerConfig {
+    LoggerConfig::builder()
+        .level(LogLevel::Error..LogLevel::Trace)
+        .memory(LogMemoryConfig::default())
+        .build()
+}
+
 #[test]
 fn logging_presets() -> Result<(), Box<dyn std::error::Error>> {
     use super::LoggingConfig;
@@ -151,3 +229,55 @@ fn rolling_debug_preset() {
 
     assert_eq!(config, expectation);
 }
+
+#[test]
+fn output_format_presets() -> Result<(), Box<dyn std::error::Error>> {
+    let config: LoggerConfig =
+        serde_json::from_str::<LoggerConfigOrPreset>(r#""json-stdout""#)?.into();
+
+    assert_eq!(
+        config,
+        LoggerConfig::builder()
+            .level(LogLevel::Info..LogLevel::Trace)
+            .console(LogTarget::Stdout)
+            .output(
+                LogFormatConfig::builder()
+                    .format(LogFormat::Json)
+                    .timestamp(LogTimestamp::Utc)
+                    .ansi(false)
+                    .build()
+            )
+            .build()
+    );
+
+    let config: LoggerConfig = serde_json::from_str(
+        r#"
+        {
+            "console": "Stderr",
+            "level": "debug",
+            "format": "compact",
+            "thread-ids": true,
+            "span-events": "close",
+            "timestamp": "local"
+        }
+        "#,
+    )?;
+
+    assert_eq!(
+        config,
+        LoggerConfig::builder()
+            .level(LogLevel::Debug)
+            .console(LogTarget::Stderr)
+            .output(
+                LogFormatConfig::builder()
+                    .format(LogFormat::Compact)
+                    .thread_ids(true)
+                    .span_events(super::LogSpanEvents::Close)
+                    .timestamp(LogTimestamp::Local)
+                    .build()
+            )
+            .build()
+    );
+
+    Ok(())
+}
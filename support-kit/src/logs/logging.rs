@@ -1,33 +1,338 @@
+use std::ops::Range;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
 use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{filter::ParseError, reload, EnvFilter, Registry};
 
-use crate::{Configuration, TracingTargets};
+use tracing::subscriber::DefaultGuard;
 
-use super::LoggingConfig;
+use crate::{
+    Configuration, LoggingError, MetricsRegistry, TracingTarget, TracingTargets, Verbosity,
+};
+
+use super::{
+    parse_directives, LogBuffer, LogLevel, LogLevelRange, LogOtlpGuard, LogRedactor, LoggingConfig,
+};
+
+pub type VerbosityFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+static HANDLE: OnceLock<LoggingHandle> = OnceLock::new();
 
 #[derive(Default)]
 pub struct Logging {
     config: LoggingConfig,
     pub loggers: TracingTargets,
-    pub guards: Vec<WorkerGuard>,
+    pub guards: Vec<LoggingGuard>,
+    pub handle: LoggingHandle,
+}
+
+/// Keeps a logger's background writer or exporter running until dropped,
+/// then flushes whatever it has left.
+#[derive(Debug)]
+pub enum LoggingGuard {
+    Appender(WorkerGuard),
+    Otlp(LogOtlpGuard),
+}
+
+impl From<WorkerGuard> for LoggingGuard {
+    fn from(guard: WorkerGuard) -> Self {
+        Self::Appender(guard)
+    }
+}
+
+impl From<LogOtlpGuard> for LoggingGuard {
+    fn from(guard: LogOtlpGuard) -> Self {
+        Self::Otlp(guard)
+    }
+}
+
+/// The filter on one running console or file logger.
+#[derive(Clone, Debug)]
+pub struct LoggerFilterHandle {
+    /// The position of the logger's config in [`Configuration::loggers`].
+    pub logger: usize,
+    pub console: bool,
+    pub handle: VerbosityFilterHandle,
+}
+
+/// Adjusts the running loggers: the filter on each console and file logger,
+/// and the level range of every logger, in the order they were configured.
+/// Clones share the same loggers and runtime changes.
+#[derive(Clone, Debug, Default)]
+pub struct LoggingHandle {
+    pub filters: Vec<LoggerFilterHandle>,
+    pub levels: Vec<LogLevelRange>,
+    /// The buffer of every memory logger.
+    pub buffers: Vec<LogBuffer>,
+    /// Masks secrets before any logger sees them.
+    pub redactor: LogRedactor,
+    state: Arc<RwLock<LoggingState>>,
+}
+
+/// The configuration last applied, and the filter set while running.
+#[derive(Debug, Default)]
+struct LoggingState {
+    config: Configuration,
+    filter: Option<String>,
+    /// Bumped on every filter change, so a temporary filter only reverts
+    /// itself and not whatever replaced it.
+    generation: u64,
+}
+
+impl LoggingHandle {
+    /// Apply the verbosity, logger filters and logger levels from the given
+    /// configuration. Loggers can't be added or removed without restarting,
+    /// so they're matched up with running loggers by position. A filter set
+    /// while running stays in place.
+    pub fn apply(&self, config: &Configuration) {
+        let loggers = config.loggers();
+
+        if let Ok(mut state) = self.state.write() {
+            state.config = config.clone();
+        }
+
+        self.reload_filters();
+
+        if loggers.len() != self.levels.len() {
+            tracing::warn!(
+                running = self.levels.len(),
+                configured = loggers.len(),
+                "logger list changed, restart to add or remove loggers"
+            );
+        }
+
+        for (levels, logger) in self.levels.iter().zip(&loggers) {
+            levels.set(logger.min_tracing_level(), logger.max_tracing_level());
+        }
+    }
+
+    /// Add `EnvFilter` directives, like `my_app=trace`, on top of every
+    /// logger's own filter until they're reset.
+    pub fn set_filter(&self, filter: &str) -> Result<(), ParseError> {
+        parse_directives(filter)?;
+        self.replace_filter(Some(filter.to_string()));
+
+        Ok(())
+    }
+
+    /// Set a filter like [`LoggingHandle::set_filter`], then put back the
+    /// previous one after `duration`, unless the filter changed since.
+    pub fn override_filter(&self, filter: &str, duration: Duration) -> Result<(), ParseError> {
+        parse_directives(filter)?;
+
+        let previous = self.filter();
+        let generation = self.replace_filter(Some(filter.to_string()));
+        let handle = self.clone();
+
+        std::thread::spawn(move || {
+            std::thread::sleep(duration);
+
+            if handle.generation() == generation {
+                tracing::info!(filter = ?previous, "log filter override expired");
+                handle.replace_filter(previous);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Drop the filter set while running.
+    pub fn reset_filter(&self) {
+        self.replace_filter(None);
+    }
+
+    /// The filter set while running, if any.
+    pub fn filter(&self) -> Option<String> {
+        self.state
+            .read()
+            .ok()
+            .and_then(|state| state.filter.clone())
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        self.state
+            .read()
+            .map(|state| state.config.verbosity)
+            .unwrap_or_default()
+    }
+
+    /// Change the verbosity of the console loggers. `RUST_LOG` still takes
+    /// precedence when it's set.
+    pub fn set_verbosity(&self, verbosity: Verbosity) {
+        if let Ok(mut state) = self.state.write() {
+            state.config.verbosity = verbosity;
+        }
+
+        self.reload_filters();
+    }
+
+    /// Step to the next verbosity, wrapping from `trace` back to `off`.
+    pub fn cycle_verbosity(&self) -> Verbosity {
+        let verbosity = self.verbosity().next();
+
+        self.set_verbosity(verbosity);
+
+        verbosity
+    }
+
+    /// Change the level range of the logger at the given position, until
+    /// the configuration is next applied.
+    pub fn set_levels(&self, logger: usize, levels: Range<LogLevel>) -> bool {
+        match self.levels.get(logger) {
+            Some(range) => {
+                range.set(levels.start.tracing_level(), levels.end.tracing_level());
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn generation(&self) -> u64 {
+        self.state
+            .read()
+            .map(|state| state.generation)
+            .unwrap_or_default()
+    }
+
+    fn replace_filter(&self, filter: Option<String>) -> u64 {
+        let generation = match self.state.write() {
+            Ok(mut state) => {
+                state.filter = filter;
+                state.generation += 1;
+                state.generation
+            }
+            Err(_) => 0,
+        };
+
+        self.reload_filters();
+
+        generation
+    }
+
+    fn reload_filters(&self) {
+        let Ok(state) = self.state.read() else {
+            return;
+        };
+        let loggers = state.config.loggers();
+        let overrides = state
+            .filter
+            .as_deref()
+            .map(parse_directives)
+            .transpose()
+            .unwrap_or_default()
+            .unwrap_or_default();
+
+        for filter in &self.filters {
+            let Some(logger) = loggers.get(filter.logger) else {
+                continue;
+            };
+            let env_filter = overrides.iter().cloned().fold(
+                logger.env_filter(&state.config, filter.console),
+                |env_filter, directive| env_filter.add_directive(directive),
+            );
+
+            if let Err(error) = filter.handle.reload(env_filter) {
+                tracing::error!(%error, "unable to apply logger filter");
+            }
+        }
+    }
 }
 
 impl Logging {
-    pub fn initialize(config: Configuration) -> Vec<WorkerGuard> {
-        use tracing_subscriber::layer::SubscriberExt;
+    /// Start every configured logger, without making them the global ones.
+    pub fn new(config: &Configuration) -> Result<Self, LoggingError> {
+        let mut logging = Self {
+            config: config.logging.clone(),
+            ..Self::default()
+        };
 
-        let mut logging = Self::default();
+        logging.handle.redactor = LogRedactor::new(config);
 
         for logger in config.loggers() {
-            logger.initialize(&config, &mut logging)
+            logger.initialize(config, &mut logging)?;
+        }
+
+        if let Ok(mut state) = logging.handle.state.write() {
+            state.config = config.clone();
         }
 
-        let subscriber = tracing_subscriber::registry().with(logging.loggers);
+        Ok(logging)
+    }
+
+    /// Take the loggers as one layer, behind the redactor, to compose with
+    /// other layers on a `Registry`. Keep the guards until it's dropped.
+    pub fn layer(&mut self) -> TracingTarget {
+        self.handle
+            .redactor
+            .layer(std::mem::take(&mut self.loggers))
+    }
+
+    /// Start the loggers and make them the global ones. Panics if a logger
+    /// can't start or a global subscriber is already set, see
+    /// [`Logging::try_initialize`].
+    pub fn initialize(config: Configuration) -> Vec<LoggingGuard> {
+        Self::try_initialize(config)
+            .unwrap_or_else(|error| panic!("Unable to start logging: {error}"))
+    }
+
+    /// Start the loggers and make them the global ones, unless one can't
+    /// start or a global subscriber is already set.
+    pub fn try_initialize(config: Configuration) -> Result<Vec<LoggingGuard>, LoggingError> {
+        Self::new(&config)?.install()
+    }
+
+    /// Start the loggers for the current thread only, until the returned
+    /// guard is dropped. Lets tests run with different loggers side by side.
+    pub fn scoped(config: &Configuration) -> Result<ScopedLogging, LoggingError> {
+        Ok(Self::new(config)?.scope())
+    }
+
+    /// Count every event a logger takes in the registry's `log_events_total`.
+    pub fn count_events(&mut self, metrics: &MetricsRegistry) {
+        self.loggers.push(metrics.layer(&self.handle.levels));
+    }
+
+    /// Make these loggers the global ones, unless a global subscriber is
+    /// already set.
+    pub fn install(mut self) -> Result<Vec<LoggingGuard>, LoggingError> {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let subscriber = tracing_subscriber::registry().with(self.layer());
+
+        tracing::subscriber::set_global_default(subscriber)?;
 
-        tracing::subscriber::set_global_default(subscriber)
-            .expect("Unable to set a global subscriber");
+        let _ = HANDLE.set(self.handle);
 
-        logging.guards
+        Ok(self.guards)
     }
+
+    /// Use these loggers on the current thread only, until the returned
+    /// guard is dropped.
+    pub fn scope(mut self) -> ScopedLogging {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let subscriber = tracing_subscriber::registry().with(self.layer());
+
+        ScopedLogging {
+            guards: self.guards,
+            handle: self.handle,
+            default: tracing::subscriber::set_default(subscriber),
+        }
+    }
+
+    /// The handle for the global loggers, once they've been initialized.
+    pub fn handle() -> Option<&'static LoggingHandle> {
+        HANDLE.get()
+    }
+}
+
+/// Loggers set for one thread by [`Logging::scoped`]. The thread goes back
+/// to its previous subscriber once this is dropped.
+#[derive(Debug)]
+pub struct ScopedLogging {
+    pub guards: Vec<LoggingGuard>,
+    pub handle: LoggingHandle,
+    pub default: DefaultGuard,
 }
 
 impl std::fmt::Debug for Logging {
@@ -45,3 +350,129 @@ impl PartialEq for Logging {
         self.config == other.config
     }
 }
+
+#[test]
+fn adjusting_loggers_while_running() {
+    use std::time::Duration;
+    use tracing::Level;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::{LogTarget, LoggerConfig, SupportControl};
+
+    let config = Configuration::builder()
+        .verbosity(Verbosity::Warn)
+        .logging(bon::vec![LoggerConfig::builder()
+            .level(LogLevel::Info)
+            .console(LogTarget::Stdout)
+            .build()])
+        .build();
+    let (filter, reload) = reload::Layer::new(config.env_filter());
+    let handle = LoggingHandle {
+        filters: vec![LoggerFilterHandle {
+            logger: 0,
+            console: true,
+            handle: reload,
+        }],
+        levels: config.loggers().iter().map(LogLevelRange::from).collect(),
+        buffers: Vec::new(),
+        redactor: Default::default(),
+        state: Default::default(),
+    };
+    let control = SupportControl::builder()
+        .args(Default::default())
+        .config(config.clone())
+        .logging(handle.clone())
+        .build();
+
+    handle.apply(&config);
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(filter), || {
+        assert!(!tracing::enabled!(target: "my_app", Level::DEBUG));
+
+        control.set_log_filter("my_app=debug").unwrap();
+        assert!(tracing::enabled!(target: "my_app", Level::DEBUG));
+        assert!(!tracing::enabled!(target: "other", Level::DEBUG));
+
+        assert!(control.set_log_filter("my_app=loud").is_err());
+        assert_eq!(handle.filter().as_deref(), Some("my_app=debug"));
+
+        handle.apply(&config);
+        assert!(tracing::enabled!(target: "my_app", Level::DEBUG));
+
+        control.reset_log_filter().unwrap();
+        assert!(!tracing::enabled!(target: "my_app", Level::DEBUG));
+
+        control
+            .override_log_filter("my_app=trace", Duration::from_millis(50))
+            .unwrap();
+        assert!(tracing::enabled!(target: "my_app", Level::TRACE));
+
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(handle.filter(), None);
+        assert!(!tracing::enabled!(target: "my_app", Level::TRACE));
+
+        assert_eq!(handle.cycle_verbosity(), Verbosity::Info);
+        assert!(tracing::enabled!(target: "other", Level::INFO));
+    });
+
+    assert!(handle.set_levels(0, LogLevel::Error..LogLevel::Warn));
+    assert_eq!(handle.levels[0].get(), (Level::ERROR, Level::WARN));
+    assert!(!handle.set_levels(1, LogLevel::Error..LogLevel::Warn));
+}
+
+#[test]
+fn initializing_scoped_loggers() {
+    use crate::{LogMemoryConfig, LoggerConfig, SupportControl};
+
+    let control = |target: &str| {
+        let config = Configuration::builder()
+            .logging(bon::vec![LoggerConfig::builder()
+                .level(LogLevel::Info)
+                .memory(LogMemoryConfig::default())
+                .filter(format!("off,{target}=info"))
+                .build()])
+            .build();
+
+        SupportControl::builder()
+            .args(Default::default())
+            .config(config)
+            .build()
+    };
+    let count = |control: &SupportControl| control.log_buffer().unwrap().events().len();
+
+    let (first, first_default) = control("first").init_scoped().unwrap();
+    tracing::info!(target: "first", "to the first");
+
+    let (second, second_default) = control("second").init_scoped().unwrap();
+    tracing::info!(target: "first", "nowhere");
+    tracing::info!(target: "second", "to the second");
+
+    drop(second_default);
+    tracing::info!(target: "first", "to the first again");
+    drop(first_default);
+
+    assert_eq!(count(&first), 2);
+    assert_eq!(count(&second), 1);
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file("logs", "not a directory")?;
+
+        let config = Configuration::builder()
+            .logging(bon::vec![LoggerConfig::builder()
+                .level(LogLevel::Info)
+                .file((jail.directory().join("logs"), "app"))
+                .build()])
+            .build();
+        let control = SupportControl::builder()
+            .args(Default::default())
+            .config(config)
+            .build();
+
+        assert!(matches!(
+            control.init_scoped(),
+            Err(LoggingError::IoError(_))
+        ));
+
+        Ok(())
+    });
+}
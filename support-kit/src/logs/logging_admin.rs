@@ -0,0 +1,290 @@
+use std::{path::Path, str::FromStr, time::Duration};
+
+use crate::{LoggingError, Verbosity};
+
+use super::LoggingHandle;
+
+/// One line sent to the logging admin socket:
+///
+/// - `status`
+/// - `filter <directives> [seconds]`, reverting after `seconds` when given
+/// - `reset`, dropping the filter
+/// - `verbosity <level>`
+/// - `cycle`, stepping to the next verbosity
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoggingCommand {
+    Status,
+    Filter(String, Option<Duration>),
+    Reset,
+    Verbosity(Verbosity),
+    Cycle,
+}
+
+impl LoggingCommand {
+    /// Run the command against the running loggers, replying with the
+    /// resulting verbosity and filter as JSON.
+    pub fn run(&self, handle: &LoggingHandle) -> Result<String, LoggingError> {
+        match self {
+            Self::Status => {}
+            Self::Filter(filter, None) => handle.set_filter(filter)?,
+            Self::Filter(filter, Some(duration)) => handle.override_filter(filter, *duration)?,
+            Self::Reset => handle.reset_filter(),
+            Self::Verbosity(verbosity) => handle.set_verbosity(*verbosity),
+            Self::Cycle => {
+                handle.cycle_verbosity();
+            }
+        }
+
+        Ok(serde_json::json!({
+            "verbosity": handle.verbosity(),
+            "filter": handle.filter(),
+        })
+        .to_string())
+    }
+}
+
+impl FromStr for LoggingCommand {
+    type Err = LoggingError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let invalid = |command: &str, argument: &str| {
+            LoggingError::InvalidArgument(command.to_string(), argument.to_string())
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["status"] => Ok(Self::Status),
+            ["reset"] => Ok(Self::Reset),
+            ["cycle"] => Ok(Self::Cycle),
+            ["filter", filter] => Ok(Self::Filter(filter.to_string(), None)),
+            ["filter", filter, seconds] => match seconds.parse() {
+                Ok(seconds) => Ok(Self::Filter(
+                    filter.to_string(),
+                    Some(Duration::from_secs(seconds)),
+                )),
+                Err(_) => Err(invalid("filter", seconds)),
+            },
+            ["verbosity", level] => serde_json::from_value(level.to_string().into())
+                .map(Self::Verbosity)
+                .map_err(|_| invalid("verbosity", level)),
+            [command @ ("filter" | "verbosity"), ..] => {
+                Err(invalid(command, &words[1..].join(" ")))
+            }
+            _ => Err(LoggingError::UnknownCommand(line.trim().to_string())),
+        }
+    }
+}
+
+/// Control over the running loggers from outside the process.
+#[derive(Clone, Debug)]
+pub struct LoggingAdmin {
+    handle: LoggingHandle,
+}
+
+impl LoggingAdmin {
+    pub fn new(handle: LoggingHandle) -> Self {
+        Self { handle }
+    }
+
+    /// Cycle the verbosity on every SIGUSR1. Has to be called from inside a
+    /// tokio runtime.
+    #[cfg(unix)]
+    pub fn listen_for_signals(&self) -> Result<tokio::task::JoinHandle<()>, LoggingError> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut signal = signal(SignalKind::user_defined1())?;
+        let handle = self.handle.clone();
+
+        Ok(tokio::spawn(async move {
+            while signal.recv().await.is_some() {
+                let verbosity = handle.cycle_verbosity();
+
+                tracing::info!(?verbosity, "verbosity changed by SIGUSR1");
+            }
+        }))
+    }
+
+    /// Serve [`LoggingCommand`]s on a Unix socket at `path` that only the
+    /// current user can connect to, replacing any stale socket but nothing
+    /// else. Each line
+    /// gets a reply line, either the status or `error: <reason>`. Has to be
+    /// called from inside a tokio runtime.
+    #[cfg(unix)]
+    pub fn serve(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<tokio::task::JoinHandle<()>, LoggingError> {
+        use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+        use tokio::net::UnixListener;
+
+        let path = path.as_ref();
+
+        if std::fs::symlink_metadata(path).is_ok_and(|metadata| !metadata.file_type().is_socket()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} exists and isn't a socket", path.display()),
+            )
+            .into());
+        }
+
+        // Bound in a directory only we can get into, so nobody else can
+        // connect before the socket's permissions are narrowed.
+        let private = path.with_file_name(format!(
+            ".{name}.{pid}",
+            name = path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default(),
+            pid = std::process::id()
+        ));
+        let bound = private.join("socket");
+
+        std::fs::DirBuilder::new().mode(0o700).create(&private)?;
+
+        let listener = UnixListener::bind(&bound)
+            .and_then(|listener| {
+                std::fs::set_permissions(&bound, std::fs::Permissions::from_mode(0o600))?;
+                std::fs::rename(&bound, path)?;
+
+                Ok(listener)
+            })
+            .inspect_err(|_| {
+                let _ = std::fs::remove_file(&bound);
+            });
+
+        std::fs::remove_dir(&private)?;
+
+        let listener = listener?;
+        let handle = self.handle.clone();
+
+        Ok(tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(respond(handle.clone(), stream));
+                    }
+                    Err(error) => {
+                        tracing::error!(%error, "unable to accept logging admin connection");
+                    }
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(unix)]
+async fn respond(handle: LoggingHandle, stream: tokio::net::UnixStream) {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = line
+            .parse::<LoggingCommand>()
+            .and_then(|command| command.run(&handle))
+            .unwrap_or_else(|error| format!("error: {error}"));
+
+        if writer
+            .write_all(format!("{reply}\n").as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+#[test]
+fn parsing_logging_commands() {
+    assert_eq!(
+        "status".parse::<LoggingCommand>().unwrap(),
+        LoggingCommand::Status
+    );
+    assert_eq!(
+        "filter my_app=trace 30".parse::<LoggingCommand>().unwrap(),
+        LoggingCommand::Filter("my_app=trace".into(), Some(Duration::from_secs(30)))
+    );
+    assert_eq!(
+        " verbosity debug ".parse::<LoggingCommand>().unwrap(),
+        LoggingCommand::Verbosity(Verbosity::Debug)
+    );
+    assert!(matches!(
+        "verbosity loud".parse::<LoggingCommand>(),
+        Err(LoggingError::InvalidArgument(..))
+    ));
+    assert!(matches!(
+        "restart".parse::<LoggingCommand>(),
+        Err(LoggingError::UnknownCommand(_))
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn serving_logging_commands() {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    figment::Jail::expect_with(|jail| {
+        let path = jail.directory().join("logging.sock");
+        let handle = LoggingHandle::default();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let replies = runtime.block_on(async {
+            LoggingAdmin::new(handle.clone()).serve(&path).unwrap();
+            LoggingAdmin::new(handle.clone()).serve(&path).unwrap();
+
+            let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut replies = Vec::new();
+
+            for command in ["filter my_app=trace", "verbosity debug", "cycle", "bogus"] {
+                writer
+                    .write_all(format!("{command}\n").as_bytes())
+                    .await
+                    .unwrap();
+                replies.push(lines.next_line().await.unwrap().unwrap());
+            }
+
+            replies
+        });
+
+        assert_eq!(
+            replies,
+            [
+                r#"{"filter":"my_app=trace","verbosity":"off"}"#,
+                r#"{"filter":"my_app=trace","verbosity":"debug"}"#,
+                r#"{"filter":"my_app=trace","verbosity":"trace"}"#,
+                r#"error: unknown logging command "bogus", expected status, filter, reset, verbosity or cycle"#,
+            ]
+        );
+        assert_eq!(handle.verbosity(), Verbosity::Trace);
+
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        jail.create_file("logging.txt", "not a socket")?;
+
+        let file = jail.directory().join("logging.txt");
+        let error = runtime
+            .block_on(async { LoggingAdmin::new(handle.clone()).serve(&file) })
+            .unwrap_err();
+
+        assert!(matches!(error, LoggingError::IoError(_)));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "not a socket");
+
+        Ok(())
+    });
+}
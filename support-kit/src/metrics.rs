@@ -0,0 +1,5 @@
+mod metrics_config;
+mod metrics_registry;
+
+pub use metrics_config::MetricsConfig;
+pub use metrics_registry::{Counter, Gauge, Histogram, MetricsRegistry};
@@ -0,0 +1,66 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::NetworkConfig;
+
+/// What to collect and where to serve it in the Prometheus text format.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, bon::Builder)]
+#[serde(rename_all = "kebab-case")]
+pub struct MetricsConfig {
+    /// Collect process metrics and count log events.
+    #[serde(default = "enabled")]
+    #[builder(default = true)]
+    pub enabled: bool,
+    /// The route metrics are served on.
+    #[serde(default = "default_path")]
+    #[builder(default = default_path(), into)]
+    pub path: String,
+    /// Serve metrics on their own address instead of the `server` one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub admin: Option<NetworkConfig>,
+    /// Prepended to every metric name but the `process_` ones, like `my_app`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(into)]
+    pub namespace: Option<String>,
+    /// Histogram bucket bounds, in ascending order.
+    #[serde(default = "default_buckets")]
+    #[builder(default = default_buckets(), into)]
+    pub buckets: Vec<f64>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn default_path() -> String {
+    "/metrics".to_string()
+}
+
+fn default_buckets() -> Vec<f64> {
+    vec![
+        0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+    ]
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+#[test]
+fn metrics_config_defaults() -> Result<(), Box<dyn std::error::Error>> {
+    let config: MetricsConfig = serde_json::from_str(r#"{ "admin": { "port": 9090 } }"#)?;
+
+    assert_eq!(
+        config,
+        MetricsConfig::builder()
+            .admin(NetworkConfig::builder().port(9090).build())
+            .build()
+    );
+    assert_eq!(config.path, "/metrics");
+    assert!(config.enabled);
+
+    Ok(())
+}
@@ -0,0 +1,580 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Instant;
+
+use tracing::subscriber::Interest;
+use tracing::{Event, Metadata, Subscriber};
+use tracing_subscriber::{filter::LevelFilter, layer::Context};
+
+use crate::{Configuration, LogLevel, LogLevelRange, TracingTarget};
+
+use super::MetricsConfig;
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// When the first registry was created, which is close enough to when the
+/// process started for anything set up through `SupportControl`.
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+type Labels = Vec<(String, String)>;
+
+/// Counters, gauges and histograms, rendered in the Prometheus text format
+/// along with process metrics. Clones share the same metrics.
+#[derive(Clone, Debug)]
+pub struct MetricsRegistry {
+    config: MetricsConfig,
+    families: Arc<RwLock<BTreeMap<String, MetricFamily>>>,
+}
+
+#[derive(Debug)]
+struct MetricFamily {
+    help: String,
+    kind: &'static str,
+    series: BTreeMap<Labels, Metric>,
+}
+
+#[derive(Clone, Debug)]
+enum Metric {
+    Counter(Counter),
+    Gauge(Gauge),
+    Histogram(Histogram),
+}
+
+impl Metric {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Counter(_) => "counter",
+            Self::Gauge(_) => "gauge",
+            Self::Histogram(_) => "histogram",
+        }
+    }
+}
+
+/// A value that only goes up.
+#[derive(Clone, Debug, Default)]
+pub struct Counter(Arc<Mutex<f64>>);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.inc_by(1.0);
+    }
+
+    /// Add to the count, ignoring negative amounts.
+    pub fn inc_by(&self, amount: f64) {
+        if let Ok(mut value) = self.0.lock() {
+            *value += amount.max(0.0);
+        }
+    }
+
+    pub fn get(&self) -> f64 {
+        self.0.lock().map(|value| *value).unwrap_or_default()
+    }
+}
+
+/// A value that goes up and down.
+#[derive(Clone, Debug, Default)]
+pub struct Gauge(Arc<Mutex<f64>>);
+
+impl Gauge {
+    pub fn set(&self, value: f64) {
+        if let Ok(mut current) = self.0.lock() {
+            *current = value;
+        }
+    }
+
+    pub fn add(&self, amount: f64) {
+        if let Ok(mut value) = self.0.lock() {
+            *value += amount;
+        }
+    }
+
+    pub fn inc(&self) {
+        self.add(1.0);
+    }
+
+    pub fn dec(&self) {
+        self.add(-1.0);
+    }
+
+    pub fn get(&self) -> f64 {
+        self.0.lock().map(|value| *value).unwrap_or_default()
+    }
+}
+
+/// Observations counted into buckets, like request durations in seconds.
+#[derive(Clone, Debug)]
+pub struct Histogram(Arc<Mutex<HistogramState>>);
+
+#[derive(Clone, Debug, Default)]
+struct HistogramState {
+    bounds: Vec<f64>,
+    /// How many observations were at most each bound.
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &[f64]) -> Self {
+        Self(Arc::new(Mutex::new(HistogramState {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len()],
+            ..Default::default()
+        })))
+    }
+
+    pub fn observe(&self, value: f64) {
+        let Ok(mut state) = self.0.lock() else {
+            return;
+        };
+        let HistogramState {
+            bounds,
+            counts,
+            sum,
+            count,
+        } = &mut *state;
+
+        for (bound, bucket) in bounds.iter().zip(counts.iter_mut()) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+
+        *sum += value;
+        *count += 1;
+    }
+
+    /// How many values were observed, and their sum.
+    pub fn get(&self) -> (u64, f64) {
+        self.0
+            .lock()
+            .map(|state| (state.count, state.sum))
+            .unwrap_or_default()
+    }
+
+    fn state(&self) -> HistogramState {
+        self.0.lock().map(|state| state.clone()).unwrap_or_default()
+    }
+}
+
+impl MetricsRegistry {
+    /// A registry with build info for the configured service.
+    pub fn new(config: &Configuration) -> Self {
+        STARTED.get_or_init(Instant::now);
+
+        let registry = Self {
+            config: config.metrics.clone(),
+            families: Default::default(),
+        };
+
+        registry
+            .gauge(
+                "build_info",
+                "The service, and the support-kit version it was built with.",
+                &[
+                    ("service", config.service.name().as_ref()),
+                    ("support_kit_version", env!("CARGO_PKG_VERSION")),
+                ],
+            )
+            .set(1.0);
+
+        registry
+    }
+
+    /// The counter with this name and labels, registered on first use.
+    pub fn counter(&self, name: &str, help: &str, labels: &[(&str, &str)]) -> Counter {
+        match self.metric(name, help, labels, Metric::Counter(Counter::default())) {
+            Metric::Counter(counter) => counter,
+            _ => unreachable!("metric kinds are checked on registration"),
+        }
+    }
+
+    /// The gauge with this name and labels, registered on first use.
+    pub fn gauge(&self, name: &str, help: &str, labels: &[(&str, &str)]) -> Gauge {
+        match self.metric(name, help, labels, Metric::Gauge(Gauge::default())) {
+            Metric::Gauge(gauge) => gauge,
+            _ => unreachable!("metric kinds are checked on registration"),
+        }
+    }
+
+    /// The histogram with this name and labels, registered on first use
+    /// with the configured buckets.
+    pub fn histogram(&self, name: &str, help: &str, labels: &[(&str, &str)]) -> Histogram {
+        let histogram = Histogram::new(&self.config.buckets);
+
+        match self.metric(name, help, labels, Metric::Histogram(histogram)) {
+            Metric::Histogram(histogram) => histogram,
+            _ => unreachable!("metric kinds are checked on registration"),
+        }
+    }
+
+    /// Every metric in the Prometheus text format, process metrics first.
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        if self.config.enabled {
+            for (name, help, value) in process_metrics() {
+                output.push_str(&format!("# HELP {name} {help}\n# TYPE {name} gauge\n"));
+                sample(&mut output, name, &[], value);
+            }
+        }
+
+        let Ok(families) = self.families.read() else {
+            return output;
+        };
+
+        for (name, family) in families.iter() {
+            output.push_str(&format!(
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n",
+                help = family.help.replace('\\', r"\\").replace('\n', r"\n"),
+                kind = family.kind
+            ));
+
+            for (labels, metric) in &family.series {
+                match metric {
+                    Metric::Counter(counter) => sample(&mut output, name, labels, counter.get()),
+                    Metric::Gauge(gauge) => sample(&mut output, name, labels, gauge.get()),
+                    Metric::Histogram(histogram) => {
+                        let state = histogram.state();
+                        let bucket = format!("{name}_bucket");
+                        let buckets = state
+                            .bounds
+                            .iter()
+                            .map(|bound| number(*bound))
+                            .zip(state.counts.iter().copied())
+                            .chain([("+Inf".to_string(), state.count)]);
+
+                        for (bound, count) in buckets {
+                            let mut labels = labels.clone();
+                            labels.push(("le".to_string(), bound));
+
+                            sample(&mut output, &bucket, &labels, count as f64);
+                        }
+
+                        sample(&mut output, &format!("{name}_sum"), labels, state.sum);
+                        sample(
+                            &mut output,
+                            &format!("{name}_count"),
+                            labels,
+                            state.count as f64,
+                        );
+                    }
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Counts events in `log_events_total` by level, at the levels at least
+    /// one of the loggers takes. Doesn't enable any events on its own.
+    pub fn layer(&self, levels: &[LogLevelRange]) -> TracingTarget {
+        let counter = |level: &str| {
+            self.counter(
+                "log_events_total",
+                "Events logged, by level.",
+                &[("level", level)],
+            )
+        };
+
+        Box::new(LogEventCounter {
+            counters: ["trace", "debug", "info", "warn", "error"].map(counter),
+            levels: levels.to_vec(),
+        })
+    }
+
+    /// Serves [`MetricsRegistry::render`] on the configured path, to merge
+    /// into an application's router or serve on its own.
+    pub fn router<S>(&self) -> axum::Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let registry = self.clone();
+
+        axum::Router::new().route(
+            &self.config.path,
+            axum::routing::get(move || {
+                let registry = registry.clone();
+
+                async move {
+                    (
+                        [(axum::http::header::CONTENT_TYPE, CONTENT_TYPE)],
+                        registry.render(),
+                    )
+                }
+            }),
+        )
+    }
+
+    /// Serve [`MetricsRegistry::router`] on its own. Has to be called from
+    /// inside a tokio runtime.
+    pub fn serve(&self, listener: tokio::net::TcpListener) -> tokio::task::JoinHandle<()> {
+        let router = self.router();
+
+        tokio::spawn(async move {
+            if let Err(error) = axum::serve(listener, router).await {
+                tracing::error!(%error, "metrics server stopped");
+            }
+        })
+    }
+
+    /// The metric registered under this name and labels, or `metric` once
+    /// it's registered. A name can only be used for one kind of metric, so
+    /// `metric` is handed back unregistered if it's the wrong one.
+    fn metric(&self, name: &str, help: &str, labels: &[(&str, &str)], metric: Metric) -> Metric {
+        let name = match &self.config.namespace {
+            Some(namespace) => format!("{namespace}_{name}"),
+            None => name.to_string(),
+        };
+        let mut labels: Labels = labels
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        labels.sort();
+
+        let Ok(mut families) = self.families.write() else {
+            return metric;
+        };
+        let family = families
+            .entry(name.clone())
+            .or_insert_with(|| MetricFamily {
+                help: help.to_string(),
+                kind: metric.kind(),
+                series: BTreeMap::new(),
+            });
+
+        if family.kind != metric.kind() {
+            tracing::warn!(
+                name,
+                registered = family.kind,
+                requested = metric.kind(),
+                "metric is already registered as another kind"
+            );
+
+            return metric;
+        }
+
+        family.series.entry(labels).or_insert(metric).clone()
+    }
+}
+
+/// Uptime, and on Linux resident memory and open file descriptors.
+fn process_metrics() -> Vec<(&'static str, &'static str, f64)> {
+    let mut metrics = vec![(
+        "process_uptime_seconds",
+        "How long the process has been running.",
+        STARTED.get_or_init(Instant::now).elapsed().as_secs_f64(),
+    )];
+
+    let resident = std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix("VmRSS:"))
+                .and_then(|line| {
+                    line.trim()
+                        .trim_end_matches("kB")
+                        .trim()
+                        .parse::<f64>()
+                        .ok()
+                })
+        });
+
+    if let Some(kilobytes) = resident {
+        metrics.push((
+            "process_resident_memory_bytes",
+            "Resident memory size in bytes.",
+            kilobytes * 1024.0,
+        ));
+    }
+
+    if let Ok(descriptors) = std::fs::read_dir("/proc/self/fd") {
+        metrics.push((
+            "process_open_fds",
+            "Number of open file descriptors.",
+            descriptors.count() as f64,
+        ));
+    }
+
+    metrics
+}
+
+fn sample(output: &mut String, name: &str, labels: &[(String, String)], value: f64) {
+    let labels = match labels.is_empty() {
+        true => String::new(),
+        false => format!(
+            "{{{}}}",
+            labels
+                .iter()
+                .map(|(name, value)| format!(
+                    "{name}=\"{value}\"",
+                    value = value
+                        .replace('\\', r"\\")
+                        .replace('"', "\\\"")
+                        .replace('\n', r"\n")
+                ))
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+    };
+
+    output.push_str(&format!("{name}{labels} {value}\n", value = number(value)));
+}
+
+fn number(value: f64) -> String {
+    match value {
+        value if value.is_nan() => "NaN".to_string(),
+        f64::INFINITY => "+Inf".to_string(),
+        f64::NEG_INFINITY => "-Inf".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Counts events in `log_events_total`, indexed by [`LogLevel`].
+struct LogEventCounter {
+    counters: [Counter; 5],
+    levels: Vec<LogLevelRange>,
+}
+
+impl LogEventCounter {
+    fn takes(&self, metadata: &Metadata<'_>) -> bool {
+        self.levels
+            .iter()
+            .any(|levels| levels.contains(metadata.level()))
+    }
+}
+
+impl<S: Subscriber> tracing_subscriber::Layer<S> for LogEventCounter {
+    /// Leave it to the loggers to decide which events are enabled.
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        Interest::never()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _context: Context<'_, S>) -> bool {
+        self.takes(metadata)
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::OFF)
+    }
+
+    fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+        if !self.takes(event.metadata()) {
+            return;
+        }
+
+        self.counters[LogLevel::from(event.metadata().level()) as usize].inc();
+    }
+}
+
+#[test]
+fn rendering_metrics() -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tracing_subscriber::{layer::SubscriberExt, Layer};
+
+    use crate::{MetricsConfig, ServiceConfig};
+
+    let config = Configuration::builder()
+        .service(ServiceConfig::builder().name("app").build())
+        .metrics(
+            MetricsConfig::builder()
+                .namespace("app")
+                .buckets(vec![0.1, 1.0])
+                .build(),
+        )
+        .build();
+    let registry = MetricsRegistry::new(&config);
+
+    registry
+        .counter("requests_total", "Requests served.", &[("method", "GET")])
+        .inc_by(2.0);
+    registry
+        .counter("requests_total", "Requests served.", &[("method", "GET")])
+        .inc();
+    registry
+        .gauge("queue_depth", "Jobs waiting.", &[("queue", "say \"hi\"")])
+        .set(4.5);
+    registry
+        .histogram("latency_seconds", "Latency.", &[])
+        .observe(0.5);
+    registry
+        .gauge("requests_total", "Wrong kind.", &[])
+        .set(9.0);
+
+    let subscriber = tracing_subscriber::registry().with(vec![
+        tracing_subscriber::fmt::layer()
+            .with_writer(std::io::sink)
+            .with_filter(LevelFilter::INFO)
+            .boxed(),
+        registry.layer(&[LogLevelRange::new(
+            tracing::Level::ERROR,
+            tracing::Level::INFO,
+        )]),
+    ]);
+
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info!("counted");
+        tracing::warn!("counted");
+        tracing::debug!("filtered out by every logger");
+    });
+
+    let rendered = registry.render();
+
+    for line in [
+        "# TYPE app_requests_total counter",
+        "app_requests_total{method=\"GET\"} 3",
+        "app_queue_depth{queue=\"say \\\"hi\\\"\"} 4.5",
+        "app_latency_seconds_bucket{le=\"0.1\"} 0",
+        "app_latency_seconds_bucket{le=\"1\"} 1",
+        "app_latency_seconds_bucket{le=\"+Inf\"} 1",
+        "app_latency_seconds_sum 0.5",
+        "app_latency_seconds_count 1",
+        "app_log_events_total{level=\"info\"} 1",
+        "app_log_events_total{level=\"warn\"} 1",
+        "app_log_events_total{level=\"debug\"} 0",
+        &format!(
+            "app_build_info{{service=\"app\",support_kit_version=\"{}\"}} 1",
+            env!("CARGO_PKG_VERSION")
+        ),
+    ] {
+        assert!(
+            rendered.lines().any(|rendered| rendered == line),
+            "{line}\n{rendered}"
+        );
+    }
+
+    assert!(rendered.contains("# TYPE process_uptime_seconds gauge"));
+    assert!(!rendered.contains("app_requests_total 9"));
+
+    #[cfg(target_os = "linux")]
+    assert!(
+        rendered.contains("process_open_fds ")
+            && rendered.contains("process_resident_memory_bytes ")
+    );
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let response = runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let server = registry.serve(listener);
+        let mut stream = tokio::net::TcpStream::connect(address).await?;
+        let mut response = String::new();
+
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await?;
+        stream.read_to_string(&mut response).await?;
+        server.abort();
+
+        Ok::<_, std::io::Error>(response)
+    })?;
+
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains(CONTENT_TYPE));
+    assert!(response.contains("app_requests_total{method=\"GET\"} 3"));
+
+    Ok(())
+}
@@ -1,12 +1,13 @@
 use std::net::SocketAddr;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::NetworkInitError;
 
 use super::{NetworkHost, NetworkPort};
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, bon::Builder)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, bon::Builder)]
 pub struct NetworkConfig {
     #[serde(default)]
     #[builder(default, into)]
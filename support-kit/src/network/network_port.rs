@@ -1,7 +1,15 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct NetworkPort(i32);
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct NetworkPort(#[schemars(range(min = 0, max = 65535))] i32);
+
+impl NetworkPort {
+    /// The port number, if it's within the valid range for a port.
+    pub fn as_u16(&self) -> Option<u16> {
+        u16::try_from(self.0).ok()
+    }
+}
 
 impl std::fmt::Display for NetworkPort {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
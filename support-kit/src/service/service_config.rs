@@ -1,9 +1,10 @@
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
 use serde::{Deserialize, Serialize};
 use service_manager::ServiceManagerKind;
 
 use super::ServiceName;
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, bon::Builder)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, bon::Builder)]
 #[serde(rename_all = "kebab-case")]
 pub struct ServiceConfig {
     #[serde(default)]
@@ -17,13 +18,26 @@ pub struct ServiceConfig {
     /// The kind of service manager to use. Defaults to system native.
     #[serde(default)]
     #[builder(into)]
+    #[schemars(schema_with = "service_manager_schema")]
     pub service_manager: Option<ServiceManagerKind>,
 }
 
+fn service_manager_schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "enum": ["launchd", "openrc", "rcd", "sc", "systemd", "winsw", null]
+    })
+}
+
 impl ServiceConfig {
     pub fn name(&self) -> ServiceName {
         self.name.clone()
     }
+
+    /// The configured service manager, or the native one when there is one.
+    pub fn manager_kind(&self) -> Option<ServiceManagerKind> {
+        self.service_manager
+            .or_else(|| ServiceManagerKind::native().ok())
+    }
 }
 
 impl From<&str> for ServiceConfig {
@@ -2,7 +2,7 @@ use service_manager::*;
 use std::ffi::OsString;
 use std::path::PathBuf;
 
-use crate::{Configuration, ServiceControlError};
+use crate::{Configuration, LoggingConfig, ServiceControlError};
 
 use super::{ServiceCommand, ServiceName};
 
@@ -10,6 +10,8 @@ pub struct ServiceControl {
     name: ServiceName,
     label: ServiceLabel,
     manager: Box<dyn ServiceManager>,
+    /// Loggers better suited to the service manager than the configured ones.
+    suggested_logging: Option<LoggingConfig>,
 }
 
 impl std::fmt::Debug for ServiceControl {
@@ -47,6 +49,8 @@ impl ServiceControl {
             name: config.name(),
             label: config.name().as_default_label()?,
             manager,
+            suggested_logging: LoggingConfig::suggested(config.service.manager_kind())
+                .filter(|suggested| *suggested != config.logging),
         })
     }
 
@@ -63,6 +67,13 @@ impl ServiceControl {
                     "installing with args"
                 );
 
+                if let Some(suggested) = &self.suggested_logging {
+                    tracing::info!(
+                        ?suggested,
+                        "consider these loggers for services under this service manager"
+                    );
+                }
+
                 self.install(self.program()?, install.args)
             }
             ServiceCommand::Start => self.start(),
@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use service_manager::ServiceLabel;
 use std::{ffi::OsStr, fmt::Display, path::Path, str::FromStr};
@@ -8,7 +9,7 @@ pub fn get_runtime_name() -> String {
     std::env::var("CARGO_PKG_NAME").unwrap_or_default()
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, PartialEq)]
 pub struct ServiceName(String);
 
 impl ServiceName {
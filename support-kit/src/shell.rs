@@ -18,6 +18,28 @@ impl ShellCommand {
         Ok(())
     }
 
+    /// Run the command and capture its standard output, without the trailing
+    /// newline.
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn output(&self) -> Result<String, ShellCommandError> {
+        tracing::trace!(command = ?self.command, "capturing shell command output");
+        let output = std::process::Command::new(&self.command)
+            .args(&self.args)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(ShellCommandError::FailedError(format!(
+                "{command} exited with {status}",
+                command = self.command,
+                status = output.status
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim_end_matches(['\r', '\n'])
+            .to_string())
+    }
+
     pub fn command_and_args(&self) -> Vec<String> {
         let mut command = vec![self.command.clone()];
         command.extend(self.args.clone());
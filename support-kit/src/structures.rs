@@ -1,8 +1,9 @@
 mod config_or_preset {
+    use schemars::JsonSchema;
     use serde::{Deserialize, Serialize};
 
     // Either a configuration (struct) or a preset (enum).
-    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+    #[derive(Clone, Debug, Deserialize, JsonSchema, Serialize, PartialEq)]
     #[serde(untagged)]
     pub enum ConfigOrPreset<Config, Preset> {
         Config(Config),
@@ -12,9 +13,10 @@ mod config_or_preset {
 
 mod one_or_many {
 
+    use schemars::JsonSchema;
     use serde::{Deserialize, Serialize};
 
-    #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+    #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
     #[serde(untagged)]
     pub enum OneOrMany<Contents> {
         Many(Vec<Contents>),
@@ -1,18 +1,34 @@
 use bon::builder;
 use figment::Figment;
 use rustls_acme::axum::AxumAcceptor;
+use serde::de::DeserializeOwned;
+use std::sync::OnceLock;
+use tracing::subscriber::DefaultGuard;
 
 use crate::{
-    Args, BoilerplateCommand, BoilerplatePreset, ConfigManifest, ConfigSources, Configuration,
-    HostControl, ShellCommand, SupportKitError,
+    Args, BoilerplateCommand, BoilerplatePreset, ConfigCommand, ConfigDiff, ConfigExplanation,
+    ConfigKey, ConfigManifest, ConfigOrigin, ConfigSearchPath, ConfigSecrets, ConfigSection,
+    ConfigSources, ConfigValidation, Configuration, EncryptedConfigFile, Environment, HostControl,
+    LogBuffer, LogEvent, LogReader, Logging, LoggingAdmin, LoggingError, LoggingHandle, LogsArgs,
+    MetricsError, MetricsRegistry, ScopedLogging, ShellCommand, SupportKitError, Verbosity,
 };
 
 #[derive(Debug, Default, bon::Builder)]
 pub struct SupportControl {
     pub args: Args,
     pub config: Configuration,
+    #[builder(default)]
+    pub secrets: ConfigSecrets,
     #[builder(default, into)]
-    _guards: Vec<tracing_appender::non_blocking::WorkerGuard>,
+    pub sections: Vec<ConfigSection>,
+    #[builder(default)]
+    pub search_path: ConfigSearchPath,
+    #[builder(default, into)]
+    _guards: Vec<crate::LoggingGuard>,
+    /// The running loggers, set by [`SupportControl::init`].
+    logging: Option<LoggingHandle>,
+    #[builder(skip)]
+    metrics: OnceLock<MetricsRegistry>,
 }
 
 #[bon::bon]
@@ -24,41 +40,317 @@ impl SupportControl {
 
     #[tracing::instrument(skip(self), level = "trace")]
     pub fn source_collection(&self) -> ConfigSources {
+        let mut search_path = self.search_path.clone();
+        search_path
+            .paths
+            .extend(self.args.config_paths.iter().cloned());
+        search_path.system |= self.config.service.system;
+
         ConfigSources::builder()
             .file(self.args.config())
-            .maybe_env(self.config.environment)
+            .maybe_env(self.config.environment.clone())
+            .search_path(search_path)
             .build()
     }
 
+    /// Every configuration layer, in the order it is merged.
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn origins(&self) -> Result<Vec<ConfigOrigin>, SupportKitError> {
+        let mut origins = vec![ConfigOrigin::from(self.config.clone())];
+        let arguments = self.args.arguments();
+
+        if !arguments.is_empty() {
+            origins.push(ConfigOrigin::Arguments(arguments));
+        }
+
+        origins.extend(self.sections.iter().cloned().map(ConfigOrigin::from));
+
+        origins.extend(
+            self.manifest()?
+                .known()
+                .definitions()
+                .iter()
+                .cloned()
+                .map(ConfigOrigin::from),
+        );
+
+        let overrides = self.args.overrides();
+
+        if !overrides.is_empty() {
+            origins.push(overrides.into());
+        }
+
+        Ok(origins)
+    }
+
     #[tracing::instrument(skip(self), level = "trace")]
     pub fn figment(&self) -> Result<Figment, SupportKitError> {
-        Ok(Figment::new()
-            .merge(&self.config)
-            .merge(&self.source_collection()))
+        Ok(self
+            .origins()?
+            .iter()
+            .fold(Figment::new(), |figment, origin| figment.merge(origin)))
+    }
+
+    /// Explain where every resolved key comes from, following the same
+    /// layering as [`SupportControl::load_configuration`].
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn explain(&self) -> Result<ConfigExplanation, SupportKitError> {
+        let initial_setup = Self::builder()
+            .args(self.args.clone())
+            .config(Configuration::from(&self.args))
+            .sections(self.sections.clone())
+            .search_path(self.search_path.clone())
+            .build();
+
+        ConfigExplanation::from_origins(&initial_setup.origins()?)
+    }
+
+    /// Compare the merged sources for two environments, or with `files` for
+    /// two config file names, with secrets redacted.
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn diff(&self, from: &str, to: &str, files: bool) -> Result<ConfigDiff, SupportKitError> {
+        let resolve = |target: &str| -> Result<(serde_json::Value, Vec<String>), SupportKitError> {
+            let mut args = self.args.clone();
+
+            match files {
+                true => args.config_file = Some(target.into()),
+                false => args.environment = Some(Environment::new(target)?),
+            }
+
+            let origins = Self::builder()
+                .args(args.clone())
+                .config(Configuration::from(&args))
+                .sections(self.sections.clone())
+                .search_path(self.search_path.clone())
+                .build()
+                .origins()?;
+            let values = origins
+                .iter()
+                .fold(Figment::new(), |figment, origin| figment.merge(origin))
+                .extract()?;
+            let redacted = ConfigExplanation::from_origins(&origins)?.redacted_keys();
+
+            Ok((values, redacted))
+        };
+
+        let (before, mut redacted) = resolve(from)?;
+        let (after, redacted_after) = resolve(to)?;
+        redacted.extend(redacted_after);
+
+        Ok(ConfigDiff::between(&before, &after)?.redact(&redacted))
+    }
+
+    /// Extract an application section from the same merged sources as the
+    /// configuration, with secret references resolved.
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn section<T: DeserializeOwned>(&self, key: &str) -> Result<T, SupportKitError> {
+        Ok(self
+            .figment()?
+            .merge(self.secrets.clone())
+            .extract_inner(key)?)
     }
 
     #[tracing::instrument(skip(args), level = "trace")]
     pub fn load_configuration(args: &Args) -> Result<Self, SupportKitError> {
+        Self::load().args(args).call()
+    }
+
+    /// Load configuration along with the application's own sections, looking
+    /// for files along the given search path as well as any `--config-path`.
+    #[builder]
+    #[tracing::instrument(skip(args), level = "trace")]
+    pub fn load(
+        args: &Args,
+        #[builder(default, into)] sections: Vec<ConfigSection>,
+        #[builder(default)] search_path: ConfigSearchPath,
+    ) -> Result<Self, SupportKitError> {
         let initial_setup = Self::builder()
             .args(args.clone())
             .config(Configuration::from(args))
+            .sections(sections.clone())
+            .search_path(search_path.clone())
             .build();
 
+        let figment = initial_setup.figment()?;
+        let secrets = ConfigSecrets::resolve(&figment)?;
         let controller = Self::builder()
             .args(args.clone())
-            .config(initial_setup.figment()?.extract()?)
+            .config(figment.merge(secrets.clone()).extract()?)
+            .secrets(secrets)
+            .sections(sections)
+            .search_path(search_path)
             .build();
 
+        if args.strict || controller.config.strict {
+            ConfigValidation::check(&initial_setup.origins()?, &controller.config)?
+                .into_result()?;
+        } else if !args.overrides().is_empty() {
+            ConfigValidation::check_overrides(&initial_setup.origins()?)?.into_result()?;
+        }
+
         tracing::debug!(sources = ?controller.manifest()?.known(), "loaded configuration with sources");
 
         Ok(controller)
     }
 
+    /// Set up color and the global loggers. Panics if a logger can't start
+    /// or a global subscriber is already set, see [`SupportControl::try_init`].
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn init(self) -> Self {
+        self.try_init()
+            .unwrap_or_else(|error| panic!("Unable to start logging: {error}"))
+    }
+
+    /// Set up color and the global loggers, unless a logger can't start or
+    /// a global subscriber is already set.
     #[tracing::instrument(skip(self), level = "trace")]
-    pub fn init(mut self) -> Self {
+    pub fn try_init(mut self) -> Result<Self, LoggingError> {
         self.config.init_color();
-        self._guards = self.config.init_logging();
-        self
+        self._guards = self.new_logging()?.install()?;
+        self.logging = Logging::handle().cloned();
+        self.add_log_secrets();
+
+        Ok(self)
+    }
+
+    /// Set up loggers for the current thread only, until the returned guard
+    /// is dropped. Color is left alone since it's global.
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn init_scoped(mut self) -> Result<(Self, DefaultGuard), LoggingError> {
+        let ScopedLogging {
+            guards,
+            handle,
+            default,
+        } = self.new_logging()?.scope();
+
+        self._guards = guards;
+        self.logging = Some(handle);
+        self.add_log_secrets();
+
+        Ok((self, default))
+    }
+
+    /// The loggers from the configuration, counting events when metrics are
+    /// enabled.
+    fn new_logging(&self) -> Result<Logging, LoggingError> {
+        let mut logging = Logging::new(&self.config)?;
+
+        if self.config.metrics.enabled {
+            logging.count_events(self.metrics());
+        }
+
+        Ok(logging)
+    }
+
+    fn add_log_secrets(&self) {
+        if let Some(logging) = &self.logging {
+            logging.redactor.add_secrets(self.secrets.values());
+        }
+    }
+
+    /// The running loggers, once logging is initialized.
+    pub fn logging(&self) -> Result<&LoggingHandle, LoggingError> {
+        match &self.logging {
+            Some(logging) => Ok(logging),
+            None => Logging::handle().ok_or(LoggingError::NotInitialized),
+        }
+    }
+
+    /// The events kept by the first memory logger.
+    pub fn log_buffer(&self) -> Result<&LogBuffer, LoggingError> {
+        self.logging()?
+            .buffers
+            .first()
+            .ok_or(LoggingError::NoLogBuffer)
+    }
+
+    /// Add `EnvFilter` directives, like `my_app=trace`, to every running
+    /// logger until [`SupportControl::reset_log_filter`].
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn set_log_filter(&self, filter: &str) -> Result<(), SupportKitError> {
+        Ok(self
+            .logging()?
+            .set_filter(filter)
+            .map_err(LoggingError::from)?)
+    }
+
+    /// Add directives to every running logger for `duration`, then put back
+    /// whatever filter was there before.
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn override_log_filter(
+        &self,
+        filter: &str,
+        duration: std::time::Duration,
+    ) -> Result<(), SupportKitError> {
+        Ok(self
+            .logging()?
+            .override_filter(filter, duration)
+            .map_err(LoggingError::from)?)
+    }
+
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn reset_log_filter(&self) -> Result<(), SupportKitError> {
+        self.logging()?.reset_filter();
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn set_verbosity(&self, verbosity: Verbosity) -> Result<(), SupportKitError> {
+        self.logging()?.set_verbosity(verbosity);
+
+        Ok(())
+    }
+
+    /// Cycle the verbosity of the running loggers on every SIGUSR1.
+    #[cfg(unix)]
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn listen_for_log_signals(&self) -> Result<tokio::task::JoinHandle<()>, SupportKitError> {
+        Ok(LoggingAdmin::new(self.logging()?.clone()).listen_for_signals()?)
+    }
+
+    /// Accept [`LoggingCommand`](crate::LoggingCommand)s for the running
+    /// loggers on a local Unix socket.
+    #[cfg(unix)]
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub fn serve_log_admin(
+        &self,
+        path: impl AsRef<std::path::Path> + std::fmt::Debug,
+    ) -> Result<tokio::task::JoinHandle<()>, SupportKitError> {
+        Ok(LoggingAdmin::new(self.logging()?.clone()).serve(path)?)
+    }
+
+    /// Counters, gauges and histograms for this service, along with process
+    /// metrics and log event counts.
+    pub fn metrics(&self) -> &MetricsRegistry {
+        self.metrics
+            .get_or_init(|| MetricsRegistry::new(&self.config))
+    }
+
+    /// Serves metrics on `metrics.path`, to merge into the application's own
+    /// router on `server`.
+    pub fn metrics_router<S>(&self) -> axum::Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        self.metrics().router()
+    }
+
+    /// Serve metrics on their own, on `metrics.admin`, or on `server` when
+    /// that isn't set. Has to be called from inside a tokio runtime.
+    #[tracing::instrument(skip(self), level = "trace")]
+    pub async fn serve_metrics(&self) -> Result<tokio::task::JoinHandle<()>, SupportKitError> {
+        let network = self
+            .config
+            .metrics
+            .admin
+            .as_ref()
+            .unwrap_or(&self.config.server);
+        let listener = tokio::net::TcpListener::bind(network.address()?)
+            .await
+            .map_err(MetricsError::from)?;
+
+        Ok(self.metrics().serve(listener))
     }
 
     #[tracing::instrument(skip(self), level = "trace")]
@@ -81,6 +373,58 @@ impl SupportControl {
         Ok(())
     }
 
+    /// Print what the file loggers wrote, here or on the deployed host given
+    /// by `--on`, and keep printing new events when following.
+    pub async fn print_logs(&self, args: &LogsArgs) -> Result<(), SupportKitError> {
+        let mut reader = match args.on.as_deref() {
+            Some(address) => {
+                let host = self
+                    .config
+                    .deployment
+                    .iter()
+                    .flat_map(|deployment| &deployment.hosts)
+                    .find(|host| host.address == address)
+                    .cloned()
+                    .ok_or_else(|| LoggingError::UnknownHost(address.to_string()))?;
+
+                LogReader::remote(&self.config, host.into()).await?
+            }
+            None => LogReader::new(&self.config),
+        };
+        let query = args.query();
+        let unfiltered = args.unfiltered();
+        let render = |line: &String| match LogEvent::from_json(line) {
+            Some(event) if query.matches(&event) => Some(match args.json {
+                true => line.clone(),
+                false => event.to_colored_string(),
+            }),
+            Some(_) => None,
+            None => unfiltered.then(|| line.clone()),
+        };
+
+        let mut lines: Vec<_> = reader.read().await?.iter().filter_map(render).collect();
+
+        if let Some(count) = args.lines {
+            lines.drain(..lines.len().saturating_sub(count));
+        }
+
+        for line in lines {
+            println!("{line}");
+        }
+
+        if !args.follow {
+            return Ok(());
+        }
+
+        loop {
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+            for line in reader.read().await?.iter().filter_map(render) {
+                println!("{line}");
+            }
+        }
+    }
+
     pub async fn execute(&self, args: Args) -> Result<(), SupportKitError> {
         match args.command {
             Some(command) => {
@@ -102,7 +446,10 @@ impl SupportControl {
                         }
                     }
                     crate::Commands::Generate(boilerplate_args) => {
-                        let control = crate::BoilerplateControl::from(self.config.clone());
+                        let control = crate::BoilerplateControl {
+                            sections: self.sections.clone(),
+                            ..crate::BoilerplateControl::from(self.config.clone())
+                        };
 
                         match boilerplate_args.command {
                             Some(operation) => match operation {
@@ -120,14 +467,67 @@ impl SupportControl {
                             }
                         }
                     }
-                    crate::Commands::Deploy(deployment_args) => match deployment_args.command {
-                        Some(operation) => operation.exec_remote(&self).await?,
-                        None => {}
-                    },
-                    crate::Commands::Container(deployment_args) => match deployment_args.command {
-                        Some(operation) => operation.exec_local(&self).await?,
-                        None => {}
+                    crate::Commands::Deploy(deployment_args) => {
+                        if let Some(operation) = deployment_args.command {
+                            operation.exec_remote(self).await?
+                        }
+                    }
+                    crate::Commands::Container(deployment_args) => {
+                        if let Some(operation) = deployment_args.command {
+                            operation.exec_local(self).await?
+                        }
+                    }
+                    crate::Commands::Config(config_args) => match config_args.command {
+                        Some(ConfigCommand::Explain) => print!("{}", self.explain()?),
+                        Some(ConfigCommand::Schema) => println!(
+                            "{}",
+                            serde_json::to_string_pretty(&Configuration::schema_with(
+                                &self.sections
+                            ))?
+                        ),
+                        Some(ConfigCommand::Encrypt { path }) => {
+                            let file = EncryptedConfigFile::encrypt_file(&path)?;
+                            println!("wrote {path}", path = file.path().display());
+                        }
+                        Some(ConfigCommand::Decrypt { path }) => {
+                            print!(
+                                "{}",
+                                EncryptedConfigFile::from_path(path)?.decrypt_to_string()?
+                            )
+                        }
+                        Some(ConfigCommand::Edit { path }) => {
+                            EncryptedConfigFile::from_path(path)?.edit()?
+                        }
+                        Some(ConfigCommand::Diff {
+                            from,
+                            to,
+                            files,
+                            json,
+                        }) => {
+                            let diff = self.diff(&from, &to, files)?;
+
+                            match json {
+                                true => println!("{}", serde_json::to_string_pretty(&diff)?),
+                                false => print!("{}", diff.to_colored_string()),
+                            }
+                        }
+                        Some(ConfigCommand::Key) => {
+                            let name = self.args.config();
+                            let variable = ConfigKey::variable(&name);
+
+                            match std::env::var_os(&variable) {
+                                Some(_) => println!("using the key set in {variable}"),
+                                None => println!(
+                                    "using the key in {path}",
+                                    path = ConfigKey::create(&name)?.display()
+                                ),
+                            }
+                        }
+                        None => {
+                            tracing::info!(config = ?self.config, "no operation provided")
+                        }
                     },
+                    crate::Commands::Logs(logs_args) => self.print_logs(&logs_args).await?,
                 }
             }
             None => tracing::trace!(config = ?&self.config, "no command provided."),
@@ -170,7 +570,7 @@ fn yaml_config_precedence_flow() {
             control.config,
             Configuration::builder()
                 .color(crate::Color::Never)
-                .environment(crate::Environment::Production)
+                .environment(crate::Environment::PRODUCTION)
                 .service(
                     crate::ServiceConfig::builder()
                         .name("app")
@@ -223,7 +623,7 @@ fn json_config_precedence_flow() {
             control.config,
             Configuration::builder()
                 .color(crate::Color::Never)
-                .environment(crate::Environment::Production)
+                .environment(crate::Environment::PRODUCTION)
                 .service(
                     crate::ServiceConfig::builder()
                         .name("app")
@@ -272,7 +672,7 @@ fn toml_config_precedence_flow() {
             control.config,
             Configuration::builder()
                 .color(crate::Color::Never)
-                .environment(crate::Environment::Production)
+                .environment(crate::Environment::PRODUCTION)
                 .service(
                     crate::ServiceConfig::builder()
                         .name("app")
@@ -286,3 +686,70 @@ fn toml_config_precedence_flow() {
         Ok(())
     });
 }
+
+#[test]
+fn inherited_environment_precedence_flow() {
+    use clap::Parser;
+
+    figment::Jail::expect_with(|jail| {
+        jail.create_file(
+            "support-kit.yaml",
+            r#"
+            environments:
+                staging:
+                    parent: production
+        "#,
+        )?;
+
+        jail.create_file(
+            "support-kit.production.yaml",
+            r#"
+            service:
+                name: app
+                system: true
+            verbosity: warn
+        "#,
+        )?;
+
+        jail.create_file(
+            "support-kit.staging.yaml",
+            r#"
+            verbosity: debug
+        "#,
+        )?;
+
+        jail.set_env("SUPPORT_KIT__PRODUCTION__COLOR", "never");
+
+        let args = Args::try_parse_from("app --environment staging".split_whitespace()).unwrap();
+        let control = SupportControl::load_configuration(&args).unwrap();
+        let staging = crate::Environment::new("staging").unwrap();
+
+        assert_eq!(
+            control.config,
+            Configuration::builder()
+                .color(crate::Color::Never)
+                .environment(staging.clone())
+                .environments(std::collections::BTreeMap::from([(
+                    staging.clone(),
+                    crate::EnvironmentConfig::builder()
+                        .parent(crate::Environment::PRODUCTION)
+                        .build(),
+                )]))
+                .service(
+                    crate::ServiceConfig::builder()
+                        .name("app")
+                        .system(true)
+                        .build()
+                )
+                .verbosity(crate::Verbosity::Debug)
+                .build()
+        );
+
+        assert_eq!(
+            control.config.environment_lineage(),
+            vec![crate::Environment::PRODUCTION, staging]
+        );
+
+        Ok(())
+    });
+}
@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(
@@ -8,6 +9,7 @@ use serde::{Deserialize, Serialize};
     PartialEq,
     Serialize,
     Deserialize,
+    JsonSchema,
     strum::FromRepr,
     strum::EnumString,
     strum::Display,
@@ -23,3 +25,10 @@ pub enum Verbosity {
     Debug,
     Trace,
 }
+
+impl Verbosity {
+    /// The next most verbose level, wrapping from `Trace` back to `Off`.
+    pub fn next(self) -> Self {
+        Self::from_repr(self as usize + 1).unwrap_or(Self::Off)
+    }
+}
@@ -0,0 +1,41 @@
+use support_kit::{
+    Configuration, LogLevel, LogMemoryConfig, LogQuery, LoggerConfig, LoggingError, SupportControl,
+};
+
+fn control(target: &str) -> SupportControl {
+    let config = Configuration::builder()
+        .logging(bon::vec![LoggerConfig::builder()
+            .level(LogLevel::Info)
+            .memory(LogMemoryConfig::default())
+            .filter(format!("off,{target}=info"))
+            .build()])
+        .build();
+
+    SupportControl::builder()
+        .args(Default::default())
+        .config(config)
+        .build()
+}
+
+#[test]
+fn initializing_global_loggers() {
+    let global = control("global").try_init().unwrap();
+
+    assert!(matches!(
+        control("again").try_init(),
+        Err(LoggingError::AlreadyInitialized(_))
+    ));
+
+    std::thread::spawn(|| tracing::info!(target: "global", "to the global one"))
+        .join()
+        .unwrap();
+
+    assert_eq!(
+        global
+            .log_buffer()
+            .unwrap()
+            .query(&LogQuery::builder().target("global").build())
+            .len(),
+        1
+    );
+}